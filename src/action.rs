@@ -1,60 +1,289 @@
 use crate::*;
 
+const HEAVY_HIT_DAMAGE: i32 = 2;
+
 #[derive(Debug)]
 pub enum Action {
   Move((i32, i32)),
-  Attack((i32, i32), i32),
+  Attack(Id, (i32, i32), (i32, i32), DamageType),
+  Shoot(Id, (i32, i32), i32, (i32, i32), DamageType),
+  Interact((i32, i32)),
+  Shove((i32, i32), i32),
+  Throw(Id, (i32, i32), i32),
+  PickUp((i32, i32)),
+  Consume(Id, Effect),
 }
 
-pub fn update_action(world: &mut World, id: Id, action: Action) {
+pub fn update_action(world: &mut World, id: Id, action: Action) -> bool {
   match action {
     Action::Move(vector) => update_move(world, id, vector),
-    Action::Attack(vector, damage) => update_attack(world, id, vector, damage),
+    Action::Attack(source_id, vector, damage, damage_type) => {
+      update_attack(world, id, source_id, vector, damage, damage_type)
+    }
+    Action::Shoot(source_id, vector, range, damage, damage_type) => {
+      update_shoot(world, id, source_id, vector, range, damage, damage_type)
+    }
+    Action::Interact(vector) => update_interact(world, id, vector),
+    Action::Shove(vector, distance) => update_shove(world, id, vector, distance),
+    Action::Throw(item_id, vector, range) => update_throw(world, id, item_id, vector, range),
+    Action::PickUp(vector) => update_pick_up(world, id, vector),
+    Action::Consume(item_id, effect) => update_consume(world, id, item_id, effect),
   }
 }
 
-fn update_move(world: &mut World, id: Id, vector: (i32, i32)) {
-  let Some(position) = world.position.get_right(&id) else {
-    return;
+pub fn target_position(world: &World, id: Id, vector: (i32, i32)) -> Option<(i32, i32)> {
+  let position = world.position.get_right(&id)?;
+  Some((position.0 + vector.0, position.1 + vector.1))
+}
+
+pub fn find_blocker(world: &World, id: Id, position: (i32, i32)) -> Option<Id> {
+  let ids = world.position.get_lefts(&position)?;
+  ids
+    .iter()
+    .find(|target_id| **target_id != id && world.solidity.contains(target_id))
+    .copied()
+}
+
+pub fn find_item(world: &World, position: (i32, i32)) -> Option<Id> {
+  let ids = world.position.get_lefts(&position)?;
+  ids
+    .iter()
+    .find(|id| world.layer.get(id) == Some(&Layer::Item))
+    .copied()
+}
+
+pub fn find_attack_target(world: &World, id: Id, position: (i32, i32)) -> Option<Id> {
+  let ids = world.position.get_lefts(&position)?;
+  ids
+    .iter()
+    .find(|target_id| **target_id != id && world.health.contains_key(target_id))
+    .copied()
+}
+
+pub fn try_move(world: &mut World, id: Id, vector: (i32, i32)) -> Result<(), Option<Id>> {
+  let origin = world.position.get_right(&id).copied().ok_or(None)?;
+  if !world.navigation.allows_step(origin, vector) {
+    return Err(None);
+  }
+  let position = (origin.0 + vector.0, origin.1 + vector.1);
+  if let Some(blocker_id) = find_blocker(world, id, position) {
+    return Err(Some(blocker_id));
+  }
+  world.position.insert(id, position);
+  Ok(())
+}
+
+pub fn can_swap_places(world: &World, id: Id, other: Id) -> bool {
+  let (Some(faction), Some(other_faction)) = (world.faction.get(&id), world.faction.get(&other))
+  else {
+    return false;
   };
-  let position = (position.0 + vector.0, position.1 + vector.1);
-  let mut is_blocked = false;
-  if let Some(ids) = world.position.get_lefts(&position) {
-    for target_id in ids.iter() {
-      if *target_id == id {
-        continue;
-      }
-      if world.solidity.contains(target_id) {
-        is_blocked = true;
+  faction == other_faction && world.ai.contains_key(&id) && is_yielding(world, other)
+}
+
+fn update_move(world: &mut World, id: Id, vector: (i32, i32)) -> bool {
+  let blocker_id = match try_move(world, id, vector) {
+    Ok(()) => return true,
+    Err(None) => return false,
+    Err(Some(blocker_id)) => blocker_id,
+  };
+  if !can_swap_places(world, id, blocker_id) {
+    return false;
+  }
+  let (Some(position), Some(blocker_position)) = (
+    world.position.get_right(&id).copied(),
+    world.position.get_right(&blocker_id).copied(),
+  ) else {
+    return false;
+  };
+  log!("ACTION", "swapped places", id, blocker_id);
+  world.position.insert(id, blocker_position);
+  world.position.insert(blocker_id, position);
+  true
+}
+
+fn update_attack(
+  world: &mut World,
+  id: Id,
+  source_id: Id,
+  vector: (i32, i32),
+  damage: (i32, i32),
+  damage_type: DamageType,
+) -> bool {
+  let Some(position) = target_position(world, id, vector) else {
+    return false;
+  };
+  let Some(target_id) = find_attack_target(world, id, position) else {
+    return false;
+  };
+  let (roll, dealt) = update_strike(world, id, source_id, target_id, damage, damage_type);
+  let knockback = if source_id == id {
+    0
+  } else {
+    item_stat(world, source_id, &world.knockback)
+  };
+  if dealt >= HEAVY_HIT_DAMAGE && knockback > 0 {
+    update_knockback(world, id, target_id, vector, knockback);
+  }
+  roll != HitRoll::Miss
+}
+
+fn update_shove(world: &mut World, id: Id, vector: (i32, i32), distance: i32) -> bool {
+  let Some(position) = target_position(world, id, vector) else {
+    return false;
+  };
+  let Some(target_id) = find_attack_target(world, id, position) else {
+    return false;
+  };
+  update_knockback(world, id, target_id, vector, distance);
+  true
+}
+
+pub fn update_knockback(
+  world: &mut World,
+  id: Id,
+  target_id: Id,
+  vector: (i32, i32),
+  distance: i32,
+) {
+  if world.health.get(&target_id).copied().unwrap_or(0) <= 0 {
+    return;
+  }
+  let vector = (vector.0.signum(), vector.1.signum());
+  for step in 0..distance {
+    match try_move(world, target_id, vector) {
+      Ok(()) => continue,
+      Err(Some(_)) => {
+        let impact = distance - step;
+        update_damage(world, id, target_id, impact, DamageType::Blunt);
         break;
       }
+      Err(None) => break,
     }
   }
-  if !is_blocked {
-    world.position.insert(id, position);
-  }
 }
 
-fn update_attack(world: &mut World, id: Id, vector: (i32, i32), damage: i32) {
-  let Some(position) = world.position.get_right(&id) else {
-    return;
+fn update_interact(world: &mut World, id: Id, vector: (i32, i32)) -> bool {
+  let Some(position) = target_position(world, id, vector) else {
+    return false;
   };
-  let position = (position.0 + vector.0, position.1 + vector.1);
-  let Some(ids) = world.position.get_lefts(&position) else {
-    return;
+  let Some(target_id) = find_interactable(world, id, position) else {
+    return false;
+  };
+  update_interaction(world, id, target_id);
+  true
+}
+
+fn update_throw(world: &mut World, id: Id, item_id: Id, offset: (i32, i32), range: i32) -> bool {
+  if world.held_by.get_right(&item_id) != Some(&id) {
+    return false;
+  }
+  let Some(origin) = world.position.get_right(&id).copied() else {
+    return false;
   };
-  let mut target_id = None;
-  for id in ids.iter() {
-    if world.health.contains_key(id) {
-      target_id = Some(*id);
+  let target = (origin.0 + offset.0, origin.1 + offset.1);
+  let (landing, hit_id) = trace_projectile(world, id, origin, target, range);
+  let (damage, damage_type) = thrown_damage(world, item_id);
+  world.held_by.remove_by_left(&item_id);
+  world.layer.insert(item_id, Layer::Item);
+  world.position.insert(item_id, landing);
+  if let Some(target_id) = hit_id {
+    update_strike(world, id, item_id, target_id, damage, damage_type);
+  }
+  true
+}
+
+pub fn trace_projectile(
+  world: &World,
+  id: Id,
+  origin: (i32, i32),
+  target: (i32, i32),
+  range: i32,
+) -> ((i32, i32), Option<Id>) {
+  let mut landing = origin;
+  for position in grid::line(origin, target).skip(1).take(range.max(0) as usize) {
+    if let Some(target_id) = find_attack_target(world, id, position) {
+      return (position, Some(target_id));
+    }
+    if find_blocker(world, id, position).is_some() {
       break;
     }
+    landing = position;
   }
-  let Some(target_id) = target_id else {
-    return;
+  (landing, None)
+}
+
+pub fn thrown_damage(world: &World, item_id: Id) -> ((i32, i32), DamageType) {
+  let bonus = item_damage(world, item_id);
+  let attack = world
+    .provides_activity
+    .get(&item_id)
+    .into_iter()
+    .flatten()
+    .filter_map(|activity| match activity.activity_type {
+      ActivityType::MeleeAttack(min, max, damage_type) => Some((min, max, damage_type)),
+      _ => None,
+    })
+    .max_by_key(|(min, max, _)| (*max, *min));
+  match attack {
+    Some((min, max, damage_type)) => ((min + bonus, max + bonus), damage_type),
+    None => ((1 + bonus, 2 + bonus), DamageType::Blunt),
+  }
+}
+
+fn update_shoot(
+  world: &mut World,
+  id: Id,
+  source_id: Id,
+  vector: (i32, i32),
+  range: i32,
+  damage: (i32, i32),
+  damage_type: DamageType,
+) -> bool {
+  let Some(origin) = world.position.get_right(&id).copied() else {
+    return false;
   };
-  let Some(health) = world.health.get(&target_id) else {
-    return;
+  let vector = (vector.0.signum(), vector.1.signum());
+  let Some(target_id) = find_shot_target(world, id, origin, vector, range) else {
+    return false;
+  };
+  update_strike(world, id, source_id, target_id, damage, damage_type).0 != HitRoll::Miss
+}
+
+pub fn find_shot_target(
+  world: &World,
+  id: Id,
+  origin: (i32, i32),
+  vector: (i32, i32),
+  range: i32,
+) -> Option<Id> {
+  let target = (origin.0 + vector.0 * range, origin.1 + vector.1 * range);
+  trace_projectile(world, id, origin, target, range).1
+}
+
+fn update_pick_up(world: &mut World, id: Id, vector: (i32, i32)) -> bool {
+  let Some(position) = target_position(world, id, vector) else {
+    return false;
   };
-  world.health.insert(target_id, (*health - damage).max(0));
+  let Some(item_id) = find_item(world, position) else {
+    return false;
+  };
+  world.position.remove_by_left(&item_id);
+  world.held_by.insert(item_id, id);
+  true
+}
+
+fn update_consume(world: &mut World, id: Id, item_id: Id, effect: Effect) -> bool {
+  update_effect(world, id, effect);
+  if item_id == id {
+    return true;
+  }
+  if let Some(charges) = world.charges.get_mut(&item_id) {
+    *charges -= 1;
+    if *charges > 0 {
+      return true;
+    }
+  }
+  world.remove_entity(&item_id);
+  true
 }
@@ -1,9 +1,11 @@
 use crate::*;
+use std::fmt;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Activity {
   pub name: &'static str,
   pub speed: usize,
+  pub cost: Option<(Resource, i32)>,
   pub activity_type: ActivityType,
 }
 
@@ -11,7 +13,69 @@ pub struct Activity {
 pub enum ActivityType {
   Wait(),
   Step(),
-  MeleeAttack(i32),
+  MeleeAttack(i32, i32, DamageType),
+  RangedAttack(i32, i32, DamageType, i32),
+  Interact(),
+  Shove(i32),
+  Throw(i32),
+  PickUp(),
+  Use(Effect),
+}
+
+impl ActivityType {
+  pub fn loudness(&self) -> usize {
+    match self {
+      Self::Wait() => 0,
+      Self::Step() => 3,
+      Self::MeleeAttack(_, _, _) => 8,
+      Self::RangedAttack(_, _, _, _) => 4,
+      Self::Interact() => 4,
+      Self::Shove(_) => 6,
+      Self::Throw(_) => 5,
+      Self::PickUp() => 1,
+      Self::Use(_) => 1,
+    }
+  }
+}
+
+pub const THROW: Activity = Activity {
+  name: "Throw",
+  speed: 5,
+  cost: None,
+  activity_type: ActivityType::Throw(6),
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityFailure {
+  Unplaced,
+  Blocked(&'static str),
+  NothingToAttack,
+  NothingToShove,
+  NothingToPickUp,
+  NoDirection,
+  NoTarget,
+  OutOfRange,
+  NothingToInteract,
+  DoorObstructed,
+  Unaffordable(Resource),
+}
+
+impl fmt::Display for ActivityFailure {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Unplaced => write!(f, "you are nowhere"),
+      Self::Blocked(name) => write!(f, "a {} blocks your way", name.to_lowercase()),
+      Self::NothingToAttack => write!(f, "nothing to attack there"),
+      Self::NothingToShove => write!(f, "nothing to shove there"),
+      Self::NothingToPickUp => write!(f, "nothing to pick up there"),
+      Self::NoDirection => write!(f, "pick a direction first"),
+      Self::NoTarget => write!(f, "pick a target first"),
+      Self::OutOfRange => write!(f, "that is out of range"),
+      Self::NothingToInteract => write!(f, "nothing to interact with there"),
+      Self::DoorObstructed => write!(f, "something is in the doorway"),
+      Self::Unaffordable(resource) => write!(f, "not enough {}", resource.name()),
+    }
+  }
 }
 
 pub fn held_items(world: &World, id: Id) -> impl Iterator<Item = &Id> {
@@ -27,4 +91,127 @@ pub fn collect_activities(world: &World, id: Id) -> impl Iterator<Item = (Id, &A
     .chain(held_items(world, id).copied())
     .filter_map(|id| world.provides_activity.get(&id).map(|a| (id, a)))
     .flat_map(|(id, activities)| activities.iter().map(move |a| (id, a)))
+    .chain(held_items(world, id).map(|item_id| (*item_id, &THROW)))
+}
+
+pub fn validate_activity(
+  world: &World,
+  id: Id,
+  activity: &Activity,
+  direction: (i32, i32),
+) -> Result<(), ActivityFailure> {
+  if let Some((resource, _)) = activity.cost {
+    if !can_afford(world, id, activity) {
+      return Err(ActivityFailure::Unaffordable(resource));
+    }
+  }
+  match activity.activity_type {
+    ActivityType::Wait() => Ok(()),
+    ActivityType::Step() => {
+      let position = target_position(world, id, direction).ok_or(ActivityFailure::Unplaced)?;
+      let origin = (position.0 - direction.0, position.1 - direction.1);
+      if !world.navigation.allows_step(origin, direction) {
+        return Err(ActivityFailure::Blocked("wall"));
+      }
+      match find_blocker(world, id, position) {
+        Some(blocker_id) if can_swap_places(world, id, blocker_id) => Ok(()),
+        Some(blocker_id) => {
+          let name = world.name.get(&blocker_id).copied().unwrap_or("something");
+          Err(ActivityFailure::Blocked(name))
+        }
+        None => Ok(()),
+      }
+    }
+    ActivityType::MeleeAttack(_, _, _) => {
+      let position = target_position(world, id, direction).ok_or(ActivityFailure::Unplaced)?;
+      find_attack_target(world, id, position)
+        .map(|_| ())
+        .ok_or(ActivityFailure::NothingToAttack)
+    }
+    ActivityType::Shove(_) => {
+      let position = target_position(world, id, direction).ok_or(ActivityFailure::Unplaced)?;
+      find_attack_target(world, id, position)
+        .map(|_| ())
+        .ok_or(ActivityFailure::NothingToShove)
+    }
+    ActivityType::RangedAttack(_, _, _, _) => {
+      if direction == (0, 0) {
+        return Err(ActivityFailure::NoDirection);
+      }
+      target_position(world, id, direction).ok_or(ActivityFailure::Unplaced)?;
+      Ok(())
+    }
+    ActivityType::Throw(range) => {
+      if direction == (0, 0) {
+        return Err(ActivityFailure::NoTarget);
+      }
+      if direction.0.abs().max(direction.1.abs()) > range {
+        return Err(ActivityFailure::OutOfRange);
+      }
+      target_position(world, id, direction).ok_or(ActivityFailure::Unplaced)?;
+      Ok(())
+    }
+    ActivityType::PickUp() => {
+      let position = target_position(world, id, direction).ok_or(ActivityFailure::Unplaced)?;
+      find_item(world, position)
+        .map(|_| ())
+        .ok_or(ActivityFailure::NothingToPickUp)
+    }
+    ActivityType::Use(_) => Ok(()),
+    ActivityType::Interact() => {
+      let position = target_position(world, id, direction).ok_or(ActivityFailure::Unplaced)?;
+      let target_id =
+        find_interactable(world, id, position).ok_or(ActivityFailure::NothingToInteract)?;
+      validate_interaction(world, target_id)
+    }
+  }
+}
+
+pub fn update_activity(
+  world: &mut World,
+  id: Id,
+  source_id: Id,
+  activity: Activity,
+  direction: (i32, i32),
+) {
+  update_activity_cost(world, id, &activity);
+  let took_effect = match activity.activity_type {
+    ActivityType::Wait() => {
+      update_rest(world, id);
+      false
+    }
+    ActivityType::Step() => {
+      update_action(world, id, Action::Move(direction))
+    }
+    ActivityType::MeleeAttack(min, max, damage_type) => {
+      let bonus = item_damage(world, source_id);
+      let damage = (min + bonus, max + bonus);
+      update_action(world, id, Action::Attack(source_id, direction, damage, damage_type))
+    }
+    ActivityType::RangedAttack(min, max, damage_type, range) => {
+      let bonus = item_damage(world, source_id);
+      let damage = (min + bonus, max + bonus);
+      update_action(world, id, Action::Shoot(source_id, direction, range, damage, damage_type))
+    }
+    ActivityType::Interact() => {
+      update_action(world, id, Action::Interact(direction))
+    }
+    ActivityType::Throw(range) => {
+      update_action(world, id, Action::Throw(source_id, direction, range))
+    }
+    ActivityType::PickUp() => {
+      update_action(world, id, Action::PickUp(direction))
+    }
+    ActivityType::Use(effect) => {
+      update_action(world, id, Action::Consume(source_id, effect))
+    }
+    ActivityType::Shove(distance) => {
+      update_action(world, id, Action::Shove(direction, distance))
+    }
+  };
+  update_noise(world, id, activity.activity_type.loudness());
+  let is_thrown = matches!(activity.activity_type, ActivityType::Throw(_));
+  if took_effect && source_id != id && !is_thrown {
+    update_item_wear(world, source_id);
+  }
 }
@@ -1,55 +1,368 @@
 use crate::*;
 
+const WANDER_RADIUS: i32 = 6;
+const SWAP_PENALTY: usize = 2;
+const PATH_SEARCH_LIMIT: usize = 512;
+const WAKE_RANGE: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiState {
+  Sleeping,
+  Wandering(Option<Point>),
+  Hunting(Id),
+  Searching(Point),
+  Fleeing(Id),
+  Returning(Point),
+}
+
+impl AiState {
+  pub const NAMES: [&'static str; 6] = ["sleep", "wander", "hunt", "search", "flee", "return"];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Sleeping => "sleep",
+      Self::Wandering(_) => "wander",
+      Self::Hunting(_) => "hunt",
+      Self::Searching(_) => "search",
+      Self::Fleeing(_) => "flee",
+      Self::Returning(_) => "return",
+    }
+  }
+}
+
 #[derive(Debug)]
 pub struct Ai {
-  // TODO switch ai to use a "target nav map" instead of a specific target id
-  pub target: Id,
+  pub state: AiState,
+  pub goals: Vec<NavigationGoal>,
+  pub flee_health_percent: i32,
+  pub range: Option<(i32, i32)>,
+}
+
+impl Ai {
+  pub fn new(state: AiState) -> Self {
+    Self {
+      state,
+      goals: vec![],
+      flee_health_percent: 50,
+      range: None,
+    }
+  }
+
+  pub fn with_goals(self, goals: Vec<NavigationGoal>) -> Self {
+    Self { goals, ..self }
+  }
+
+  pub fn with_flee_health_percent(self, flee_health_percent: i32) -> Self {
+    Self {
+      flee_health_percent,
+      ..self
+    }
+  }
+
+  pub fn with_range(self, min: i32, max: i32) -> Self {
+    Self {
+      range: Some((min, max)),
+      ..self
+    }
+  }
+
+  pub fn navigation_map<'a>(&self, world: &'a World, target: Id) -> Option<&'a Navigation> {
+    let goal = goal_for_target(world, target)?;
+    if !self.goals.contains(&goal) {
+      return None;
+    }
+    world.navigation_maps.get(&goal)
+  }
+
+  pub fn flee_map<'a>(&self, world: &'a World, threat: Id) -> Option<&'a Navigation> {
+    let goal = goal_for_target(world, threat)?;
+    if !self.goals.contains(&goal) {
+      return None;
+    }
+    world.flee_maps.get(&goal)
+  }
+
+  pub fn errand_map<'a>(
+    &self,
+    world: &'a World,
+    id: Id,
+    position: Point,
+  ) -> Option<&'a Navigation> {
+    let remembers_items = world.memory.get(&id).is_some_and(|memory| !memory.items.is_empty());
+    self
+      .goals
+      .iter()
+      .filter(|goal| match goal {
+        NavigationGoal::Items => remembers_items,
+        NavigationGoal::Exits => true,
+        _ => false,
+      })
+      .filter_map(|goal| world.navigation_maps.get(goal))
+      .find(|navigation| navigation.get_finite_value(position).is_some())
+  }
+
+  pub fn hear(&mut self, origin: Point) {
+    if matches!(self.state, AiState::Hunting(_) | AiState::Fleeing(_)) {
+      return;
+    }
+    self.state = AiState::Searching(origin);
+  }
+}
+
+impl Default for Ai {
+  fn default() -> Self {
+    Self::new(AiState::Wandering(None))
+  }
+}
+
+pub fn update_ai_state(world: &mut World, id: Id) {
+  let Some(state) = world.ai.get(&id).map(|ai| ai.state) else {
+    return;
+  };
+  let Some(position) = world.position.get_right(&id).copied() else {
+    return;
+  };
+  let target = find_hostile_target(world, id);
+  let is_near = |target: Id| {
+    let target_position = world.position.get_right(&target);
+    target_position.is_some_and(|p| chebyshev(position, *p) <= WAKE_RANGE)
+  };
+  let state = match (state, target) {
+    (AiState::Sleeping, Some(target)) if is_near(target) => AiState::Hunting(target),
+    (AiState::Sleeping, _) => AiState::Sleeping,
+    (_, Some(target)) if is_badly_hurt(world, id) => AiState::Fleeing(target),
+    (_, Some(target)) => AiState::Hunting(target),
+    (AiState::Fleeing(_), None) => idle_state(world, id),
+    (AiState::Hunting(target), None) => {
+      let last_seen = world.memory.get(&id).and_then(|memory| memory.last_seen(target));
+      match last_seen {
+        Some(sighting) => AiState::Searching(sighting.position),
+        None => idle_state(world, id),
+      }
+    }
+    (AiState::Searching(goal), None) if chebyshev(position, goal) <= 1 => idle_state(world, id),
+    (AiState::Returning(post), None) if chebyshev(position, post) <= 1 => AiState::Wandering(None),
+    (AiState::Wandering(Some(goal)), None) if position != goal => AiState::Wandering(Some(goal)),
+    (AiState::Wandering(_), None) => AiState::Wandering(pick_wander_goal(world, position)),
+    (state, None) => state,
+  };
+  if let Some(ai) = world.ai.get_mut(&id) {
+    if ai.state != state {
+      log!("AI", "state changed", id, ai.state, state);
+    }
+    ai.state = state;
+  }
+}
+
+fn idle_state(world: &World, id: Id) -> AiState {
+  match world.memory.get(&id).and_then(|memory| memory.post) {
+    Some(post) => AiState::Returning(post),
+    None => AiState::Wandering(None),
+  }
 }
 
-pub fn pick_ai_action(world: &World, id: Id) -> (usize, Option<Action>) {
+pub fn is_badly_hurt(world: &World, id: Id) -> bool {
+  let (Some(health), Some(maximum)) = (world.health.get(&id), world.max_health.get(&id)) else {
+    return false;
+  };
   let Some(ai) = world.ai.get(&id) else {
-    return (1, None);
-  };
-  let Some(position) = world.position.get_right(&id) else {
-    return (1, None);
-  };
-  let Some(navigation) = world.navigation.best_neighbor(*position) else {
-    return (1, None);
-  };
-  let (desired_position, remaining_steps) = navigation;
-  let activities = collect_activities(world, id).collect::<Vec<_>>();
-  let mut speed = 1;
-  let mut action = None;
-  if let Some(move_speed) = pick_step(&activities) {
-    let move_vector = (desired_position.0 - position.0, desired_position.1 - position.1);
-    speed = move_speed;
-    action = Some(Action::Move(move_vector));
-    if remaining_steps == 0 {
-      if let Some((attack_speed, attack_damage)) = pick_melee_attack(&activities) {
-        speed = attack_speed;
-        action = Some(Action::Attack(move_vector, attack_damage));
+    return false;
+  };
+  *health * 100 <= *maximum * ai.flee_health_percent
+}
+
+pub fn ranged_band(world: &World, id: Id) -> Option<(i32, i32)> {
+  let band = world.ai.get(&id)?.range?;
+  collect_activities(world, id)
+    .any(|(_, activity)| matches!(activity.activity_type, ActivityType::RangedAttack(..)))
+    .then_some(band)
+}
+
+pub fn chebyshev(a: Point, b: Point) -> i32 {
+  (a.0 - b.0).abs().max((a.1 - b.1).abs())
+}
+
+fn pick_wander_goal(world: &mut World, position: Point) -> Option<Point> {
+  for _ in 0..8 {
+    let goal = (
+      position.0 + world.random.range(-WANDER_RADIUS, WANDER_RADIUS + 1),
+      position.1 + world.random.range(-WANDER_RADIUS, WANDER_RADIUS + 1),
+    );
+    if goal != position && world.navigation.get_value(goal).is_some() {
+      return Some(goal);
+    }
+  }
+  None
+}
+
+pub fn desired_position(world: &World, id: Id) -> Option<Point> {
+  let ai = world.ai.get(&id)?;
+  let position = *world.position.get_right(&id)?;
+  match ai.state {
+    AiState::Sleeping => None,
+    AiState::Wandering(goal) => match ai.errand_map(world, id, position) {
+      Some(navigation) => descend_position(world, id, navigation, position),
+      None => goal.and_then(|goal| approach_position(world, id, position, goal)),
+    },
+    AiState::Searching(goal) | AiState::Returning(goal) => path_position(world, id, position, goal),
+    AiState::Hunting(target) => {
+      let target_position = *world.position.get_right(&target)?;
+      let navigation = ai.navigation_map(world, target);
+      match (ranged_band(world, id), navigation) {
+        (Some(band), _) => ranged_position(world, id, navigation, position, target, band),
+        (None, Some(navigation)) => {
+          crowd_position(world, id, navigation, position, target_position)
+        }
+        (None, None) => approach_position(world, id, position, target_position),
+      }
+    }
+    AiState::Fleeing(threat) => {
+      let threat_position = *world.position.get_right(&threat)?;
+      match ai.flee_map(world, threat) {
+        Some(navigation) => descend_position(world, id, navigation, position),
+        None => retreat_position(world, id, position, threat_position),
       }
     }
   }
-  (speed, action)
 }
 
-fn pick_step(activities: &[(Id, &Activity)]) -> Option<usize> {
-  activities
-    .iter()
-    .find(|(_, activity)| matches!(activity.activity_type, ActivityType::Step()))
-    .map(|(_, activity)| activity.speed)
+fn open_neighbors(world: &World, id: Id, position: Point) -> impl Iterator<Item = Point> + '_ {
+  world
+    .navigation
+    .neighbors(position)
+    .into_iter()
+    .map(|(p, _)| p)
+    .filter(|p| world.navigation.get_value(*p).is_some())
+    .filter(move |p| knows_closed_door_at(world, id, *p) || find_blocker(world, id, *p).is_none())
 }
 
-fn pick_melee_attack(activities: &[(Id, &Activity)]) -> Option<(usize, i32)> {
-  activities
-    .iter()
-    .filter_map(|(_, activity)| {
-      if let ActivityType::MeleeAttack(damage) = activity.activity_type {
-        Some((activity.speed, damage))
-      } else {
-        None
+fn path_position(world: &World, id: Id, position: Point, goal: Point) -> Option<Point> {
+  if position == goal {
+    return None;
+  }
+  let passable = |p| {
+    if world.navigation.get_value(p).is_none() {
+      return false;
+    }
+    let is_felt = chebyshev(position, p) <= 1;
+    !is_felt || knows_closed_door_at(world, id, p) || find_blocker(world, id, p).is_none()
+  };
+  let cost = |_, to| if knows_closed_door_at(world, id, to) { 2 } else { 1 };
+  let mut pathfinder = Pathfinder::new(passable, cost);
+  pathfinder.diagonal_cost = world.navigation.diagonal_cost;
+  pathfinder.cut_corners = world.navigation.cut_corners;
+  pathfinder.search_limit = PATH_SEARCH_LIMIT;
+  match pathfinder.find(position, goal).and_then(|mut path| path.next()) {
+    Some(next) => Some(next),
+    None => approach_position(world, id, position, goal),
+  }
+}
+
+fn approach_position(world: &World, id: Id, position: Point, target_position: Point) -> Option<Point> {
+  open_neighbors(world, id, position).min_by_key(|p| {
+    let delta = (target_position.0 - p.0, target_position.1 - p.1);
+    (chebyshev(*p, target_position), delta.0.pow(2) + delta.1.pow(2))
+  })
+}
+
+fn crowd_position(
+  world: &World,
+  id: Id,
+  navigation: &Navigation,
+  position: Point,
+  target_position: Point,
+) -> Option<Point> {
+  let value = navigation.get_value(position)?;
+  navigation
+    .neighbors(position)
+    .into_iter()
+    .filter(|(p, _)| *p != target_position)
+    .filter_map(|(p, _)| {
+      let neighbor_value = navigation.get_value(p).filter(|v| *v < value)?;
+      match find_blocker(world, id, p) {
+        None => Some((p, neighbor_value)),
+        Some(_) if knows_closed_door_at(world, id, p) => Some((p, neighbor_value)),
+        Some(ally) if can_swap_places(world, id, ally) => {
+          Some((p, neighbor_value + SWAP_PENALTY))
+        }
+        Some(_) => None,
       }
     })
-    .next()
+    .min_by_key(|(p, v)| {
+      let delta = (target_position.0 - p.0, target_position.1 - p.1);
+      (*v, delta.0.pow(2) + delta.1.pow(2))
+    })
+    .map(|(p, _)| p)
+}
+
+fn ranged_position(
+  world: &World,
+  id: Id,
+  navigation: Option<&Navigation>,
+  position: Point,
+  target: Id,
+  band: (i32, i32),
+) -> Option<Point> {
+  let target_position = *world.position.get_right(&target)?;
+  let distance = |p: Point| match navigation.and_then(|n| n.get_finite_value(p)) {
+    Some(value) => value as i32,
+    None => chebyshev(p, target_position),
+  };
+  let penalty = |p: Point| {
+    let distance = distance(p);
+    let band_penalty = if distance < band.0 {
+      (band.0 - distance) * 2
+    } else {
+      (distance - band.1).max(0)
+    };
+    let shot_penalty = if has_shot(world, id, p, target, band.1) { 0 } else { 3 };
+    band_penalty + shot_penalty
+  };
+  let best = open_neighbors(world, id, position)
+    .filter(|p| *p != target_position)
+    .min_by_key(|p| (penalty(*p), chebyshev(*p, target_position)))?;
+  if penalty(best) < penalty(position) {
+    Some(best)
+  } else {
+    None
+  }
+}
+
+fn has_shot(world: &World, id: Id, origin: Point, target: Id, range: i32) -> bool {
+  let Some(target_position) = world.position.get_right(&target) else {
+    return false;
+  };
+  let delta = (target_position.0 - origin.0, target_position.1 - origin.1);
+  let is_aligned = delta.0 == 0 || delta.1 == 0 || delta.0.abs() == delta.1.abs();
+  if !is_aligned || delta == (0, 0) {
+    return false;
+  }
+  let vector = (delta.0.signum(), delta.1.signum());
+  find_shot_target(world, id, origin, vector, range) == Some(target)
+}
+
+pub fn is_yielding(world: &World, id: Id) -> bool {
+  world
+    .ai
+    .get(&id)
+    .is_some_and(|ai| !matches!(ai.state, AiState::Hunting(_)))
+}
+
+fn descend_position(world: &World, id: Id, navigation: &Navigation, position: Point) -> Option<Point> {
+  let value = navigation.get_value(position)?;
+  open_neighbors(world, id, position)
+    .filter_map(|p| navigation.get_value(p).map(|v| (p, v)))
+    .filter(|(_, v)| *v < value)
+    .min_by_key(|(_, v)| *v)
+    .map(|(p, _)| p)
+}
+
+fn retreat_position(world: &World, id: Id, position: Point, threat_position: Point) -> Option<Point> {
+  let distance = |p: Point| {
+    let delta = (threat_position.0 - p.0, threat_position.1 - p.1);
+    (chebyshev(p, threat_position), delta.0.pow(2) + delta.1.pow(2))
+  };
+  open_neighbors(world, id, position)
+    .filter(|p| distance(*p) > distance(position))
+    .max_by_key(|p| distance(*p))
 }
@@ -0,0 +1,496 @@
+use crate::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+const INDENT: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+  Hurt,
+  EnemyVisible,
+  EnemyAdjacent,
+  State(String),
+  Not(Box<Condition>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BehaviorAction {
+  Wait,
+  Step,
+  Open,
+  Melee,
+  Shoot,
+  Shove,
+  Throw,
+  PickUp,
+  Use,
+  Named(String),
+}
+
+impl BehaviorAction {
+  fn matches(&self, activity: &Activity) -> bool {
+    match (self, activity.activity_type) {
+      (Self::Wait, ActivityType::Wait()) => true,
+      (Self::Step, ActivityType::Step()) => true,
+      (Self::Open, ActivityType::Interact()) => true,
+      (Self::Melee, ActivityType::MeleeAttack(..)) => true,
+      (Self::Shoot, ActivityType::RangedAttack(..)) => true,
+      (Self::Shove, ActivityType::Shove(_)) => true,
+      (Self::Throw, ActivityType::Throw(_)) => true,
+      (Self::PickUp, ActivityType::PickUp()) => true,
+      (Self::Use, ActivityType::Use(_)) => true,
+      (Self::Named(name), _) => activity.name.eq_ignore_ascii_case(name),
+      _ => false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BehaviorNode {
+  Sequence(Vec<BehaviorNode>),
+  Selector(Vec<BehaviorNode>),
+  Condition(Condition),
+  Action(BehaviorAction),
+  Cooldown(usize, usize, Box<BehaviorNode>),
+}
+
+#[derive(Debug)]
+pub struct BehaviorTree {
+  pub name: String,
+  pub root: BehaviorNode,
+}
+
+#[derive(Debug)]
+pub struct Behavior {
+  pub tree: Rc<BehaviorTree>,
+  pub ready_at: HashMap<usize, usize>,
+}
+
+impl Behavior {
+  pub fn new(tree: Rc<BehaviorTree>) -> Self {
+    Self {
+      tree,
+      ready_at: HashMap::new(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BehaviorError {
+  UnknownNode(usize, String),
+  UnknownCondition(usize, String),
+  UnknownAction(usize, String),
+  BadIndent(usize),
+  MissingChild(usize),
+  ExtraChild(usize),
+  MissingTree(usize),
+  InvalidCooldown(usize, String),
+}
+
+impl fmt::Display for BehaviorError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::UnknownNode(line, word) => write!(f, "line {line}: unknown node '{word}'"),
+      Self::UnknownCondition(line, word) => write!(f, "line {line}: unknown condition '{word}'"),
+      Self::UnknownAction(line, word) => write!(f, "line {line}: unknown action '{word}'"),
+      Self::BadIndent(line) => write!(f, "line {line}: unexpected indentation"),
+      Self::MissingChild(line) => write!(f, "line {line}: node needs a child"),
+      Self::ExtraChild(line) => write!(f, "line {line}: node takes no children"),
+      Self::MissingTree(line) => write!(f, "line {line}: expected 'tree <name>'"),
+      Self::InvalidCooldown(line, word) => write!(f, "line {line}: invalid cooldown '{word}'"),
+    }
+  }
+}
+
+struct Line<'a> {
+  number: usize,
+  depth: usize,
+  words: Vec<&'a str>,
+}
+
+pub fn parse_behavior_trees(
+  source: &str,
+) -> Result<HashMap<String, Rc<BehaviorTree>>, BehaviorError> {
+  let mut lines = vec![];
+  for (index, text) in source.lines().enumerate() {
+    let number = index + 1;
+    let content = text.split('#').next().unwrap_or("").trim_end();
+    if content.trim().is_empty() {
+      continue;
+    }
+    let indent = content.len() - content.trim_start().len();
+    if indent % INDENT != 0 {
+      return Err(BehaviorError::BadIndent(number));
+    }
+    lines.push(Line {
+      number,
+      depth: indent / INDENT,
+      words: content.split_whitespace().collect(),
+    });
+  }
+  let mut trees = HashMap::new();
+  let mut index = 0;
+  while index < lines.len() {
+    let line = &lines[index];
+    let name = match line.words.as_slice() {
+      ["tree", name] if line.depth == 0 => name.to_string(),
+      _ => return Err(BehaviorError::MissingTree(line.number)),
+    };
+    let number = line.number;
+    index += 1;
+    if lines.get(index).map(|l| l.depth) != Some(1) {
+      return Err(BehaviorError::MissingChild(number));
+    }
+    let mut cooldowns = 0;
+    let root = parse_node(&lines, &mut index, &mut cooldowns)?;
+    if lines.get(index).is_some_and(|l| l.depth > 0) {
+      return Err(BehaviorError::ExtraChild(number));
+    }
+    trees.insert(name.clone(), Rc::new(BehaviorTree { name, root }));
+  }
+  Ok(trees)
+}
+
+fn parse_node(
+  lines: &[Line],
+  index: &mut usize,
+  cooldowns: &mut usize,
+) -> Result<BehaviorNode, BehaviorError> {
+  let line = &lines[*index];
+  *index += 1;
+  let mut children = vec![];
+  while lines.get(*index).is_some_and(|l| l.depth > line.depth) {
+    if lines[*index].depth != line.depth + 1 {
+      return Err(BehaviorError::BadIndent(lines[*index].number));
+    }
+    children.push(parse_node(lines, index, cooldowns)?);
+  }
+  let leaf = |node: BehaviorNode, children: &Vec<BehaviorNode>| {
+    if children.is_empty() {
+      Ok(node)
+    } else {
+      Err(BehaviorError::ExtraChild(line.number))
+    }
+  };
+  match line.words.as_slice() {
+    ["sequence"] | ["selector"] if children.is_empty() => {
+      Err(BehaviorError::MissingChild(line.number))
+    }
+    ["sequence"] => Ok(BehaviorNode::Sequence(children)),
+    ["selector"] => Ok(BehaviorNode::Selector(children)),
+    ["cooldown", duration] => {
+      let duration = duration
+        .parse()
+        .map_err(|_| BehaviorError::InvalidCooldown(line.number, duration.to_string()))?;
+      if children.len() != 1 {
+        return Err(BehaviorError::MissingChild(line.number));
+      }
+      let key = *cooldowns;
+      *cooldowns += 1;
+      Ok(BehaviorNode::Cooldown(
+        duration,
+        key,
+        Box::new(children.remove(0)),
+      ))
+    }
+    ["condition", words @ ..] => {
+      let condition = parse_condition(line.number, words)?;
+      leaf(BehaviorNode::Condition(condition), &children)
+    }
+    ["action", word] => {
+      let action = parse_action(line.number, word)?;
+      leaf(BehaviorNode::Action(action), &children)
+    }
+    ["activity", words @ ..] if !words.is_empty() => {
+      let action = BehaviorAction::Named(words.join(" "));
+      leaf(BehaviorNode::Action(action), &children)
+    }
+    words => {
+      let word = words.first().copied().unwrap_or("");
+      Err(BehaviorError::UnknownNode(line.number, word.to_string()))
+    }
+  }
+}
+
+fn parse_condition(number: usize, words: &[&str]) -> Result<Condition, BehaviorError> {
+  match words {
+    ["not", rest @ ..] => Ok(Condition::Not(Box::new(parse_condition(number, rest)?))),
+    ["hurt"] => Ok(Condition::Hurt),
+    ["enemy-visible"] => Ok(Condition::EnemyVisible),
+    ["enemy-adjacent"] => Ok(Condition::EnemyAdjacent),
+    ["state", name] if AiState::NAMES.contains(name) => Ok(Condition::State(name.to_string())),
+    words => Err(BehaviorError::UnknownCondition(number, words.join(" "))),
+  }
+}
+
+fn parse_action(number: usize, word: &str) -> Result<BehaviorAction, BehaviorError> {
+  match word {
+    "wait" => Ok(BehaviorAction::Wait),
+    "step" => Ok(BehaviorAction::Step),
+    "open" => Ok(BehaviorAction::Open),
+    "melee" => Ok(BehaviorAction::Melee),
+    "shoot" => Ok(BehaviorAction::Shoot),
+    "shove" => Ok(BehaviorAction::Shove),
+    "throw" => Ok(BehaviorAction::Throw),
+    "pickup" => Ok(BehaviorAction::PickUp),
+    "use" => Ok(BehaviorAction::Use),
+    _ => Err(BehaviorError::UnknownAction(number, word.to_string())),
+  }
+}
+
+pub fn attach_behavior(world: &mut World, id: Id, name: &str) -> bool {
+  let Some(tree) = world.behavior_trees.get(name).cloned() else {
+    return false;
+  };
+  world.behavior.insert(id, Behavior::new(tree));
+  true
+}
+
+struct Decision {
+  plan: Plan,
+  cooldowns: Vec<usize>,
+}
+
+pub fn run_behavior(world: &World, id: Id) -> Option<(Plan, Vec<usize>)> {
+  let behavior = world.behavior.get(&id)?;
+  let plans = score_ai_activities(world, id);
+  let decision = evaluate(world, id, behavior, &plans, &behavior.tree.root).ok()??;
+  Some((decision.plan, decision.cooldowns))
+}
+
+pub fn update_behavior_cooldowns(world: &mut World, id: Id, cooldowns: &[usize]) {
+  let Some(behavior) = world.behavior.get_mut(&id) else {
+    return;
+  };
+  for key in cooldowns {
+    let duration = find_cooldown(&behavior.tree.root, *key).unwrap_or(0);
+    behavior.ready_at.insert(*key, world.time + duration);
+  }
+}
+
+fn find_cooldown(node: &BehaviorNode, key: usize) -> Option<usize> {
+  match node {
+    BehaviorNode::Cooldown(duration, k, _) if *k == key => Some(*duration),
+    BehaviorNode::Cooldown(_, _, child) => find_cooldown(child, key),
+    BehaviorNode::Sequence(children) | BehaviorNode::Selector(children) => {
+      children.iter().find_map(|child| find_cooldown(child, key))
+    }
+    _ => None,
+  }
+}
+
+fn evaluate(
+  world: &World,
+  id: Id,
+  behavior: &Behavior,
+  plans: &[Plan],
+  node: &BehaviorNode,
+) -> Result<Option<Decision>, ()> {
+  match node {
+    BehaviorNode::Sequence(children) => {
+      for child in children {
+        if let Some(decision) = evaluate(world, id, behavior, plans, child)? {
+          return Ok(Some(decision));
+        }
+      }
+      Ok(None)
+    }
+    BehaviorNode::Selector(children) => {
+      let mut result = Err(());
+      for child in children {
+        match evaluate(world, id, behavior, plans, child) {
+          Ok(Some(decision)) => return Ok(Some(decision)),
+          Ok(None) => result = Ok(None),
+          Err(()) => continue,
+        }
+      }
+      result
+    }
+    BehaviorNode::Condition(condition) => {
+      if check_condition(world, id, condition) {
+        Ok(None)
+      } else {
+        Err(())
+      }
+    }
+    BehaviorNode::Action(action) => {
+      let plan = plans
+        .iter()
+        .find(|plan| action.matches(&plan.activity))
+        .ok_or(())?;
+      Ok(Some(Decision {
+        plan: *plan,
+        cooldowns: vec![],
+      }))
+    }
+    BehaviorNode::Cooldown(_, key, child) => {
+      if behavior
+        .ready_at
+        .get(key)
+        .is_some_and(|time| *time > world.time)
+      {
+        return Err(());
+      }
+      let mut result = evaluate(world, id, behavior, plans, child)?;
+      if let Some(decision) = result.as_mut() {
+        decision.cooldowns.push(*key);
+      }
+      Ok(result)
+    }
+  }
+}
+
+fn check_condition(world: &World, id: Id, condition: &Condition) -> bool {
+  let opponent = world.ai.get(&id).and_then(|ai| match ai.state {
+    AiState::Hunting(target) | AiState::Fleeing(target) => Some(target),
+    _ => None,
+  });
+  match condition {
+    Condition::Hurt => is_badly_hurt(world, id),
+    Condition::EnemyVisible => opponent.is_some_and(|target| can_perceive(world, id, target)),
+    Condition::EnemyAdjacent => {
+      let position = world.position.get_right(&id);
+      let target_position = opponent.and_then(|target| world.position.get_right(&target));
+      match (position, target_position) {
+        (Some(a), Some(b)) => chebyshev(*a, *b) == 1,
+        _ => false,
+      }
+    }
+    Condition::State(name) => world.ai.get(&id).is_some_and(|ai| ai.state.name() == name),
+    Condition::Not(condition) => !check_condition(world, id, condition),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_error(source: &str) -> BehaviorError {
+    parse_behavior_trees(source).unwrap_err()
+  }
+
+  #[test]
+  fn parses_the_shipped_trees() {
+    let trees = parse_behavior_trees(include_str!("../data/behaviors.txt")).unwrap();
+    let mut names = trees.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["brute", "coward", "skirmisher"]);
+  }
+
+  #[test]
+  fn parses_nested_nodes_and_skips_comments() {
+    let source = concat!(
+      "# comment\n",
+      "\n",
+      "tree t\n",
+      "  selector\n",
+      "    sequence  # inline\n",
+      "      condition not hurt\n",
+      "      action melee\n",
+      "    cooldown 5\n",
+      "      activity Big Swing\n",
+    );
+    let trees = parse_behavior_trees(source).unwrap();
+    let expected = BehaviorNode::Selector(vec![
+      BehaviorNode::Sequence(vec![
+        BehaviorNode::Condition(Condition::Not(Box::new(Condition::Hurt))),
+        BehaviorNode::Action(BehaviorAction::Melee),
+      ]),
+      BehaviorNode::Cooldown(
+        5,
+        0,
+        Box::new(BehaviorNode::Action(BehaviorAction::Named("Big Swing".into()))),
+      ),
+    ]);
+    assert_eq!(trees["t"].root, expected);
+  }
+
+  #[test]
+  fn rejects_indentation_that_is_not_two_spaces() {
+    assert_eq!(parse_error("tree t\n   action wait\n"), BehaviorError::BadIndent(2));
+  }
+
+  #[test]
+  fn rejects_indentation_that_skips_a_level() {
+    let source = "tree t\n  selector\n      action wait\n";
+    assert_eq!(parse_error(source), BehaviorError::BadIndent(3));
+  }
+
+  #[test]
+  fn rejects_nodes_outside_a_tree() {
+    assert_eq!(parse_error("  action wait\n"), BehaviorError::MissingTree(1));
+    assert_eq!(parse_error("tree\n  action wait\n"), BehaviorError::MissingTree(1));
+  }
+
+  #[test]
+  fn rejects_nodes_without_children() {
+    assert_eq!(parse_error("tree t\ntree u\n"), BehaviorError::MissingChild(1));
+    assert_eq!(parse_error("tree t\n  sequence\n"), BehaviorError::MissingChild(2));
+    let source = "tree t\n  cooldown 3\n    action wait\n    action step\n";
+    assert_eq!(parse_error(source), BehaviorError::MissingChild(2));
+  }
+
+  #[test]
+  fn rejects_extra_children() {
+    let source = "tree t\n  action wait\n    action step\n";
+    assert_eq!(parse_error(source), BehaviorError::ExtraChild(2));
+    let source = "tree t\n  action wait\n  action step\n";
+    assert_eq!(parse_error(source), BehaviorError::ExtraChild(1));
+  }
+
+  #[test]
+  fn rejects_unknown_words() {
+    let error = parse_error("tree t\n  repeat\n");
+    assert_eq!(error, BehaviorError::UnknownNode(2, "repeat".into()));
+    let error = parse_error("tree t\n  condition angry\n");
+    assert_eq!(error, BehaviorError::UnknownCondition(2, "angry".into()));
+    let error = parse_error("tree t\n  action dance\n");
+    assert_eq!(error, BehaviorError::UnknownAction(2, "dance".into()));
+    let error = parse_error("tree t\n  cooldown soon\n    action wait\n");
+    assert_eq!(error, BehaviorError::InvalidCooldown(2, "soon".into()));
+  }
+
+  #[test]
+  fn rejects_unknown_state_names() {
+    let trees = parse_behavior_trees("tree t\n  condition state flee\n").unwrap();
+    assert_eq!(trees["t"].root, BehaviorNode::Condition(Condition::State("flee".into())));
+    let error = parse_error("tree t\n  condition state fle\n");
+    assert_eq!(error, BehaviorError::UnknownCondition(2, "state fle".into()));
+  }
+
+  #[test]
+  fn reports_the_line_number() {
+    let error = parse_error("tree t\n  action dance\n");
+    assert_eq!(error.to_string(), "line 2: unknown action 'dance'");
+  }
+
+  #[test]
+  fn selector_keeps_looking_past_a_child_that_picks_nothing() {
+    let mut world = World::default();
+    let id = Id::new();
+    world.ai.insert(id, Ai::default());
+    let source = concat!(
+      "tree t\n",
+      "  selector\n",
+      "    sequence\n",
+      "      condition state wander\n",
+      "    action wait\n",
+    );
+    let tree = parse_behavior_trees(source).unwrap().remove("t").unwrap();
+    let behavior = Behavior::new(tree);
+    let plans = [Plan {
+      source_id: id,
+      activity: Activity {
+        name: "Wait",
+        speed: 10,
+        cost: None,
+        activity_type: ActivityType::Wait(),
+      },
+      direction: (0, 0),
+      score: 1,
+    }];
+    let decision = evaluate(&world, id, &behavior, &plans, &behavior.tree.root).unwrap();
+    assert_eq!(decision.map(|d| d.plan.activity.name), Some("Wait"));
+  }
+}
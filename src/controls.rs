@@ -5,6 +5,11 @@ pub struct Controls {
   pub act_left: char,
   pub act_right: char,
   pub act_center: char,
+  pub act_up_left: Option<char>,
+  pub act_up_right: Option<char>,
+  pub act_down_left: Option<char>,
+  pub act_down_right: Option<char>,
   pub activity_previous: char,
   pub activity_next: char,
+  pub travel_down: Option<char>,
 }
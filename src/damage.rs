@@ -0,0 +1,310 @@
+use crate::*;
+use std::fmt;
+
+const BASE_HIT_CHANCE: i32 = 80;
+const BASE_CRITICAL_CHANCE: i32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageType {
+  Slashing,
+  Blunt,
+  Piercing,
+  Fire,
+}
+
+impl DamageType {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Slashing => "slashing",
+      Self::Blunt => "blunt",
+      Self::Piercing => "piercing",
+      Self::Fire => "fire",
+    }
+  }
+
+  pub fn is_physical(&self) -> bool {
+    matches!(self, Self::Slashing | Self::Blunt | Self::Piercing)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageStage {
+  Outgoing,
+  Armor,
+  Resistance,
+}
+
+pub const DAMAGE_STAGES: [DamageStage; 3] = [
+  DamageStage::Outgoing,
+  DamageStage::Armor,
+  DamageStage::Resistance,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageModifier {
+  Bonus(i32),
+  Armor(i32),
+  Resistance(DamageType, i32),
+}
+
+impl DamageModifier {
+  pub fn stage(&self) -> DamageStage {
+    match self {
+      Self::Bonus(_) => DamageStage::Outgoing,
+      Self::Armor(_) => DamageStage::Armor,
+      Self::Resistance(_, _) => DamageStage::Resistance,
+    }
+  }
+
+  pub fn is_harmful(&self) -> bool {
+    match self {
+      Self::Bonus(amount) | Self::Armor(amount) | Self::Resistance(_, amount) => *amount < 0,
+    }
+  }
+
+  pub fn label(&self) -> &'static str {
+    match self {
+      Self::Bonus(_) => "Bonus",
+      Self::Armor(_) => "Armor",
+      Self::Resistance(_, _) => "Resist",
+    }
+  }
+
+  fn apply(&self, damage_type: DamageType, amount: i32) -> i32 {
+    match *self {
+      Self::Bonus(bonus) => amount + bonus,
+      Self::Armor(armor) if damage_type.is_physical() => amount - armor,
+      Self::Armor(_) => amount,
+      Self::Resistance(t, percent) if t == damage_type => amount - amount * percent / 100,
+      Self::Resistance(_, _) => amount,
+    }
+  }
+}
+
+impl fmt::Display for DamageModifier {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Bonus(amount) => write!(f, "damage {amount:+}"),
+      Self::Armor(amount) => write!(f, "armor {amount:+}"),
+      Self::Resistance(t, percent) => write!(f, "{} {percent:+}%", t.name()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitRoll {
+  Miss,
+  Hit,
+  Critical,
+}
+
+#[derive(Debug, Clone)]
+pub struct DamageStep {
+  pub label: &'static str,
+  pub source: &'static str,
+  pub amount: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hit {
+  pub attacker: &'static str,
+  pub defender: &'static str,
+  pub damage_type: DamageType,
+  pub chance: i32,
+  pub roll: HitRoll,
+  pub steps: Vec<DamageStep>,
+  pub total: i32,
+}
+
+pub fn collect_damage_modifiers(world: &World, id: Id) -> Vec<(Id, DamageModifier)> {
+  let mut modifiers = equipment(world, id)
+    .filter_map(|id| world.damage_modifiers.get(&id).map(|m| (id, m)))
+    .flat_map(|(id, modifiers)| modifiers.iter().map(move |m| (id, *m)))
+    .chain(
+      world
+        .statuses
+        .get(&id)
+        .into_iter()
+        .flat_map(|statuses| statuses.iter())
+        .map(|status| (id, status.modifier)),
+    )
+    .collect::<Vec<_>>();
+  modifiers.sort_by_key(|(id, _)| *id);
+  modifiers
+}
+
+pub fn calculate_damage(
+  world: &World,
+  attacker: Id,
+  defender: Id,
+  amount: i32,
+  damage_type: DamageType,
+  chance: i32,
+  roll: HitRoll,
+) -> Hit {
+  let name = |id| world.name.get(&id).copied().unwrap_or("???");
+  let mut hit = Hit {
+    attacker: name(attacker),
+    defender: name(defender),
+    damage_type,
+    chance,
+    roll,
+    steps: vec![],
+    total: 0,
+  };
+  if roll == HitRoll::Miss {
+    return hit;
+  }
+  hit.steps.push(DamageStep {
+    label: "Base",
+    source: name(attacker),
+    amount,
+  });
+  hit.total = amount;
+  if roll == HitRoll::Critical {
+    hit.steps.push(DamageStep {
+      label: "Critical",
+      source: name(attacker),
+      amount,
+    });
+    hit.total += amount;
+  }
+  for stage in DAMAGE_STAGES {
+    let modifiers = match stage {
+      DamageStage::Outgoing => collect_damage_modifiers(world, attacker),
+      DamageStage::Armor | DamageStage::Resistance => collect_damage_modifiers(world, defender),
+    };
+    for (source, modifier) in modifiers {
+      if modifier.stage() != stage {
+        continue;
+      }
+      let total = modifier.apply(damage_type, hit.total).max(0);
+      if total == hit.total {
+        continue;
+      }
+      hit.steps.push(DamageStep {
+        label: modifier.label(),
+        source: name(source),
+        amount: total - hit.total,
+      });
+      hit.total = total;
+    }
+  }
+  hit
+}
+
+pub fn hit_chance(world: &World, attacker: Id, source_id: Id, defender: Id) -> i32 {
+  let accuracy = attack_stat(world, attacker, source_id, &world.accuracy);
+  let evasion = equipment_stat(world, defender, &world.evasion);
+  (BASE_HIT_CHANCE + accuracy - evasion).clamp(5, 95)
+}
+
+pub fn critical_chance(world: &World, attacker: Id, source_id: Id) -> i32 {
+  let critical = attack_stat(world, attacker, source_id, &world.critical);
+  (BASE_CRITICAL_CHANCE + critical).clamp(0, 100)
+}
+
+pub fn update_strike(
+  world: &mut World,
+  attacker: Id,
+  source_id: Id,
+  defender: Id,
+  damage: (i32, i32),
+  damage_type: DamageType,
+) -> (HitRoll, i32) {
+  let chance = hit_chance(world, attacker, source_id, defender);
+  let roll = if !world.random.chance(chance) {
+    HitRoll::Miss
+  } else if world.random.chance(critical_chance(world, attacker, source_id)) {
+    HitRoll::Critical
+  } else {
+    HitRoll::Hit
+  };
+  let amount = world.random.range(damage.0, damage.1 + 1);
+  let hit = calculate_damage(world, attacker, defender, amount, damage_type, chance, roll);
+  let total = hit.total;
+  update_hit(world, attacker, defender, &hit);
+  world.last_hit = Some(hit);
+  world.last_impact = None;
+  (roll, total)
+}
+
+pub fn update_damage(
+  world: &mut World,
+  attacker: Id,
+  defender: Id,
+  amount: i32,
+  damage_type: DamageType,
+) {
+  let hit = calculate_damage(world, attacker, defender, amount, damage_type, 100, HitRoll::Hit);
+  update_hit(world, attacker, defender, &hit);
+  world.last_impact = Some(hit);
+}
+
+fn update_hit(world: &mut World, attacker: Id, defender: Id, hit: &Hit) {
+  if let Some(health) = world.health.get_mut(&defender) {
+    let was_alive = *health > 0;
+    *health = (*health - hit.total).max(0);
+    if was_alive && *health == 0 {
+      update_kill_experience(world, attacker, defender);
+    }
+  }
+  log!("DAMAGE", "resolved hit", hit);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn combatants() -> (World, Id, Id) {
+    let mut world = World::default();
+    let attacker = Id::new();
+    let defender = Id::new();
+    world.name.insert(attacker, "Attacker");
+    world.name.insert(defender, "Defender");
+    world.damage_modifiers.insert(attacker, DamageModifier::Bonus(1));
+    world.damage_modifiers.insert(defender, DamageModifier::Armor(2));
+    world
+      .damage_modifiers
+      .insert(defender, DamageModifier::Resistance(DamageType::Fire, 50));
+    (world, attacker, defender)
+  }
+
+  fn labels(hit: &Hit) -> Vec<&'static str> {
+    hit.steps.iter().map(|step| step.label).collect()
+  }
+
+  #[test]
+  fn misses_deal_nothing() {
+    let (world, attacker, defender) = combatants();
+    let hit = calculate_damage(&world, attacker, defender, 3, DamageType::Blunt, 80, HitRoll::Miss);
+    assert_eq!(hit.total, 0);
+    assert!(hit.steps.is_empty());
+  }
+
+  #[test]
+  fn physical_hits_apply_bonus_then_armor() {
+    let (world, attacker, defender) = combatants();
+    let hit = calculate_damage(&world, attacker, defender, 3, DamageType::Blunt, 80, HitRoll::Hit);
+    assert_eq!(labels(&hit), vec!["Base", "Bonus", "Armor"]);
+    assert_eq!(hit.total, 2);
+  }
+
+  #[test]
+  fn critical_fire_hits_skip_armor_and_apply_resistance() {
+    let (world, attacker, defender) = combatants();
+    let hit =
+      calculate_damage(&world, attacker, defender, 3, DamageType::Fire, 80, HitRoll::Critical);
+    assert_eq!(labels(&hit), vec!["Base", "Critical", "Bonus", "Resist"]);
+    assert_eq!(hit.total, 4);
+  }
+
+  #[test]
+  fn armor_never_heals() {
+    let (mut world, attacker, defender) = combatants();
+    world.damage_modifiers.remove_by_left(&attacker);
+    let hit =
+      calculate_damage(&world, attacker, defender, 1, DamageType::Slashing, 80, HitRoll::Hit);
+    assert_eq!(hit.total, 0);
+  }
+}
@@ -0,0 +1,45 @@
+use crate::*;
+
+#[derive(Debug)]
+pub struct Door {
+  pub is_open: bool,
+}
+
+pub fn find_door(world: &World, position: (i32, i32)) -> Option<Id> {
+  let ids = world.position.get_lefts(&position)?;
+  ids.iter().find(|id| world.door.contains_key(id)).copied()
+}
+
+pub fn is_closed_door_at(world: &World, position: (i32, i32)) -> bool {
+  let Some(id) = find_door(world, position) else {
+    return false;
+  };
+  world.door.get(&id).map(|door| !door.is_open).unwrap_or(false)
+}
+
+pub fn update_door(world: &mut World, id: Id, is_open: bool) {
+  let Some(door) = world.door.get_mut(&id) else {
+    return;
+  };
+  door.is_open = is_open;
+  if is_open {
+    world.icon.insert(id, '\'');
+    world.solidity.remove(&id);
+    world.opacity.remove(&id);
+  } else {
+    world.icon.insert(id, '+');
+    world.solidity.insert(id);
+    world.opacity.insert(id);
+  }
+}
+
+pub fn spawn_door(world: &mut World, position: (i32, i32), is_open: bool) -> Id {
+  let id = Id::new();
+  world.name.insert(id, "door");
+  world.layer.insert(id, Layer::Feature);
+  world.position.insert(id, position);
+  world.door.insert(id, Door { is_open });
+  world.interaction.insert(id, Interaction::Door);
+  update_door(world, id, is_open);
+  id
+}
@@ -0,0 +1,267 @@
+use crate::*;
+use std::collections::{HashMap, HashSet};
+
+type SpawnMonster = fn(&mut World, char, Point) -> Id;
+
+const LEVEL_MONSTERS: usize = 5;
+const MONSTER_KINDS: [(char, SpawnMonster); 5] = [
+  ('G', spawn_goblin),
+  ('N', spawn_goblin),
+  ('T', spawn_goblin),
+  ('K', spawn_goblin),
+  ('A', spawn_goblin_archer),
+];
+const ARRIVAL_RADIUS: i32 = 10;
+
+#[derive(Default)]
+pub struct Dungeon {
+  pub seed: u32,
+  pub depth: usize,
+  pub stairs_up: HashMap<usize, (i32, i32)>,
+  pub stairs_down: HashMap<usize, (i32, i32)>,
+  levels: HashMap<usize, Level>,
+}
+
+impl Dungeon {
+  pub fn new(seed: u32) -> Self {
+    Self {
+      seed,
+      ..Default::default()
+    }
+  }
+}
+
+struct Actor {
+  id: Id,
+  ai: Option<Ai>,
+  fov: Option<FieldOfView>,
+  health: Option<i32>,
+  stamina: Option<Pool>,
+  mana: Option<Pool>,
+  memory: Option<Memory>,
+  behavior: Option<Behavior>,
+  statuses: Option<HashSet<Status>>,
+}
+
+struct Level {
+  positions: Vec<(Id, (i32, i32))>,
+  actors: Vec<Actor>,
+  events: Vec<(usize, Event)>,
+  navigation: Navigation,
+  explored: HashSet<Point>,
+  suspended_at: usize,
+}
+
+pub fn generate_level(world: &mut World, depth: usize) {
+  let seed = hash_u32(world.dungeon.seed.wrapping_add(depth as u32));
+  let mut random = Random::new(seed);
+  let open_cells = mapping::cavern(world, seed, 10000, 10000);
+  let pick_cell = |random: &mut Random| {
+    let index = random.range(0, open_cells.len() as i32) as usize;
+    open_cells[index]
+  };
+  if depth > 0 {
+    spawn_stairs(world, (0, 0), -1);
+    world.dungeon.stairs_up.insert(depth, (0, 0));
+  }
+  let stairs_down = pick_cell(&mut random);
+  spawn_stairs(world, stairs_down, 1);
+  world.dungeon.stairs_down.insert(depth, stairs_down);
+  if depth == 0 {
+    return;
+  }
+  let mut behaviors = world.behavior_trees.keys().cloned().collect::<Vec<_>>();
+  behaviors.sort();
+  for i in 0..LEVEL_MONSTERS {
+    let position = pick_cell(&mut random);
+    if position.0.abs() < 10 && position.1.abs() < 10 {
+      continue;
+    }
+    let (icon, spawn) = MONSTER_KINDS[i % MONSTER_KINDS.len()];
+    let id = spawn(world, icon, position);
+    if !behaviors.is_empty() {
+      // The extra choice past the last tree leaves the monster on the plain planner.
+      let no_behavior = behaviors.len() as i32;
+      let index = random.range(0, no_behavior + 1);
+      if index != no_behavior {
+        attach_behavior(world, id, &behaviors[index as usize]);
+      }
+    }
+    if random.bool() {
+      if let Some(ai) = world.ai.get_mut(&id) {
+        ai.state = AiState::Sleeping;
+      }
+    }
+  }
+}
+
+fn spawn_stairs(world: &mut World, position: (i32, i32), delta: i32) -> Id {
+  let id = Id::new();
+  let (name, icon) = if delta < 0 {
+    ("stairs up", '<')
+  } else {
+    ("stairs down", '>')
+  };
+  world.name.insert(id, name);
+  world.icon.insert(id, icon);
+  world.layer.insert(id, Layer::Feature);
+  world.position.insert(id, position);
+  world.interaction.insert(id, Interaction::Stairs(delta));
+  id
+}
+
+pub fn update_travel(world: &mut World, id: Id, delta: i32) {
+  let depth = world.dungeon.depth as i32 + delta;
+  if depth < 0 || id != world.view_target {
+    return;
+  }
+  let depth = depth as usize;
+  let Some(origin) = world.position.get_right(&id).copied() else {
+    return;
+  };
+  let faction = world.faction.get(&id).copied();
+  let mut travelers = vec![id];
+  for (follower_id, _) in world.ai.iter() {
+    if faction.is_none() || world.faction.get(follower_id).copied() != faction {
+      continue;
+    }
+    let Some(position) = world.position.get_right(follower_id) else {
+      continue;
+    };
+    let distance = (position.0 - origin.0).abs().max((position.1 - origin.1).abs());
+    if distance <= 1 {
+      travelers.push(*follower_id);
+    }
+  }
+  let traveler_events = suspend_level(world, &travelers);
+  resume_level(world, depth);
+  world.navigation_seeds.clear();
+  let arrival = if delta < 0 {
+    world.dungeon.stairs_down.get(&depth)
+  } else {
+    world.dungeon.stairs_up.get(&depth)
+  };
+  let arrival = arrival.copied().unwrap_or((0, 0));
+  place_travelers(world, &travelers, arrival);
+  for (time, event) in traveler_events {
+    world.timeline.push(time, event);
+  }
+  world.message(format!("you arrive at depth {}", depth + 1));
+}
+
+fn suspend_level(world: &mut World, travelers: &[Id]) -> Vec<(usize, Event)> {
+  let positions = world
+    .position
+    .iter()
+    .filter(|(id, _)| !travelers.contains(id))
+    .map(|(id, position)| (*id, *position))
+    .collect::<Vec<_>>();
+  let mut actors = vec![];
+  for (id, _) in positions.iter() {
+    world.position.remove_by_left(id);
+    if world.ai.contains_key(id) || world.health.contains_key(id) {
+      actors.push(suspend_actor(world, *id));
+    }
+  }
+  let mut traveler_events = vec![];
+  let mut events = vec![];
+  for (time, event) in world.timeline.drain() {
+    if travelers.contains(&event.id()) {
+      traveler_events.push((time, event));
+    } else {
+      events.push((time, event));
+    }
+  }
+  let level = Level {
+    positions,
+    actors,
+    events,
+    navigation: world.navigation.take_cells(),
+    explored: std::mem::take(&mut world.explored),
+    suspended_at: world.time,
+  };
+  world.dungeon.levels.insert(world.dungeon.depth, level);
+  traveler_events
+}
+
+fn resume_level(world: &mut World, depth: usize) {
+  world.dungeon.depth = depth;
+  let Some(level) = world.dungeon.levels.remove(&depth) else {
+    generate_level(world, depth);
+    return;
+  };
+  for (id, position) in level.positions {
+    world.position.insert(id, position);
+  }
+  let elapsed = world.time - level.suspended_at;
+  for actor in level.actors {
+    resume_actor(world, actor, elapsed);
+  }
+  for (time, event) in level.events {
+    world.timeline.push(time + elapsed, event);
+  }
+  world.navigation = level.navigation;
+  world.explored = level.explored;
+}
+
+fn suspend_actor(world: &mut World, id: Id) -> Actor {
+  Actor {
+    id,
+    ai: world.ai.remove(&id),
+    fov: world.fov.remove(&id),
+    health: world.health.remove(&id),
+    stamina: world.stamina.remove(&id),
+    mana: world.mana.remove(&id),
+    memory: world.memory.remove(&id),
+    behavior: world.behavior.remove(&id),
+    statuses: world.statuses.remove_by_left(&id),
+  }
+}
+
+fn resume_actor(world: &mut World, actor: Actor, elapsed: usize) {
+  let id = actor.id;
+  if let Some(ai) = actor.ai {
+    world.ai.insert(id, ai);
+  }
+  if let Some(fov) = actor.fov {
+    world.fov.insert(id, fov);
+  }
+  if let Some(health) = actor.health {
+    world.health.insert(id, health);
+  }
+  // Pools count the ticks they have regenerated, so they resume as they were.
+  if let Some(stamina) = actor.stamina {
+    world.stamina.insert(id, stamina);
+  }
+  if let Some(mana) = actor.mana {
+    world.mana.insert(id, mana);
+  }
+  if let Some(mut memory) = actor.memory {
+    memory.delay(elapsed);
+    world.memory.insert(id, memory);
+  }
+  if let Some(mut behavior) = actor.behavior {
+    for ready_at in behavior.ready_at.values_mut() {
+      *ready_at += elapsed;
+    }
+    world.behavior.insert(id, behavior);
+  }
+  for status in actor.statuses.into_iter().flatten() {
+    let status = Status {
+      expires_at: status.expires_at + elapsed,
+      ..status
+    };
+    world.statuses.insert(id, status);
+  }
+}
+
+fn place_travelers(world: &mut World, travelers: &[Id], arrival: (i32, i32)) {
+  for id in travelers.iter() {
+    world.position.remove_by_left(id);
+    let position = grid::spiral(arrival, ARRIVAL_RADIUS)
+      .filter(|p| world.navigation.get_value(*p).is_some())
+      .find(|p| find_blocker(world, *id, *p).is_none())
+      .unwrap_or(arrival);
+    world.position.insert(*id, position);
+  }
+}
@@ -0,0 +1,57 @@
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Effect {
+  Heal(i32),
+  Cure,
+  Buff(DamageModifier, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Status {
+  pub modifier: DamageModifier,
+  pub expires_at: usize,
+}
+
+pub fn update_effect(world: &mut World, id: Id, effect: Effect) {
+  match effect {
+    Effect::Heal(amount) => {
+      let maximum = world.max_health.get(&id).copied().unwrap_or(i32::MAX);
+      if let Some(health) = world.health.get_mut(&id) {
+        *health = (*health + amount).min(maximum);
+      }
+    }
+    Effect::Cure => {
+      let harmful = world
+        .statuses
+        .get(&id)
+        .into_iter()
+        .flat_map(|statuses| statuses.iter())
+        .filter(|status| status.modifier.is_harmful())
+        .copied()
+        .collect::<Vec<_>>();
+      for status in harmful {
+        world.statuses.remove_by_right(&id, &status);
+      }
+    }
+    Effect::Buff(modifier, duration) => {
+      let status = Status {
+        modifier,
+        expires_at: world.time + duration,
+      };
+      world.statuses.insert(id, status);
+    }
+  }
+}
+
+pub fn update_statuses(world: &mut World) {
+  let expired = world
+    .statuses
+    .iter()
+    .flat_map(|(id, statuses)| statuses.iter().map(move |status| (*id, *status)))
+    .filter(|(_, status)| status.expires_at <= world.time)
+    .collect::<Vec<_>>();
+  for (id, status) in expired {
+    world.statuses.remove_by_right(&id, &status);
+  }
+}
@@ -5,6 +5,14 @@ pub enum Event {
   Turn(Id, TurnType),
 }
 
+impl Event {
+  pub fn id(&self) -> Id {
+    match self {
+      Event::Turn(id, _) => *id,
+    }
+  }
+}
+
 pub fn update_current_event(world: &mut World) {
   let Some(current_event) = world.current_event.take() else {
     return;
@@ -0,0 +1,75 @@
+use crate::*;
+use std::collections::HashSet;
+
+const SIGHT_RANGE: i32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Faction {
+  Player,
+  Goblin,
+  Beast,
+  Villager,
+}
+
+pub struct Hostility {
+  pairs: HashSet<(Faction, Faction)>,
+}
+
+impl Hostility {
+  pub fn new() -> Self {
+    Self {
+      pairs: HashSet::new(),
+    }
+  }
+
+  pub fn is_hostile(&self, a: Faction, b: Faction) -> bool {
+    self.pairs.contains(&(a, b))
+  }
+
+  pub fn set_hostile(&mut self, a: Faction, b: Faction, is_hostile: bool) {
+    if is_hostile {
+      self.pairs.insert((a, b));
+      self.pairs.insert((b, a));
+    } else {
+      self.pairs.remove(&(a, b));
+      self.pairs.remove(&(b, a));
+    }
+  }
+}
+
+impl Default for Hostility {
+  fn default() -> Self {
+    let mut hostility = Self::new();
+    hostility.set_hostile(Faction::Player, Faction::Goblin, true);
+    hostility.set_hostile(Faction::Villager, Faction::Goblin, true);
+    hostility
+  }
+}
+
+pub fn is_hostile(world: &World, a: Id, b: Id) -> bool {
+  let (Some(a), Some(b)) = (world.faction.get(&a), world.faction.get(&b)) else {
+    return false;
+  };
+  world.hostility.is_hostile(*a, *b)
+}
+
+pub fn can_perceive(world: &World, id: Id, other: Id) -> bool {
+  can_see(world, id, other).unwrap_or_else(|| has_line_of_sight(world, id, other, SIGHT_RANGE))
+}
+
+pub fn find_hostile_target(world: &World, id: Id) -> Option<Id> {
+  let position = world.position.get_right(&id)?;
+  world
+    .faction
+    .iter()
+    .map(|(other_id, _)| *other_id)
+    .filter(|other_id| *other_id != id && world.health.contains_key(other_id))
+    .filter(|other_id| is_hostile(world, id, *other_id))
+    .filter(|other_id| can_perceive(world, id, *other_id))
+    .filter_map(|other_id| world.position.get_right(&other_id).map(|p| (other_id, *p)))
+    .min_by_key(|(other_id, p)| {
+      let distance = (p.0 - position.0).abs().max((p.1 - position.1).abs());
+      (distance, *other_id)
+    })
+    .map(|(other_id, _)| other_id)
+}
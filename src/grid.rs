@@ -1,5 +1,9 @@
 pub type Point = (i32, i32);
 
+pub const ORTHOGONAL_DIRECTIONS: [Point; 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+pub const DIAGONAL_DIRECTIONS: [Point; 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
 pub struct LineIter {
   next: Point,
   remaining_steps: i32,
@@ -0,0 +1,132 @@
+use crate::*;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+  Door,
+  Lever,
+  Sign(&'static str),
+  Chest,
+  Talk(&'static str),
+  Stairs(i32),
+}
+
+#[derive(Debug)]
+pub struct Lever {
+  pub is_pulled: bool,
+}
+
+pub fn find_interactable(world: &World, id: Id, position: (i32, i32)) -> Option<Id> {
+  let ids = world.position.get_lefts(&position)?;
+  let mut ids = ids
+    .iter()
+    .filter(|target_id| **target_id != id && world.interaction.contains_key(target_id))
+    .collect::<Vec<_>>();
+  ids.sort_by_key(|id| std::cmp::Reverse(world.layer.get(id)));
+  ids.first().copied().copied()
+}
+
+pub fn validate_interaction(world: &World, target_id: Id) -> Result<(), ActivityFailure> {
+  let Some(interaction) = world.interaction.get(&target_id) else {
+    return Err(ActivityFailure::NothingToInteract);
+  };
+  match interaction {
+    Interaction::Door => {
+      let is_open = world.door.get(&target_id).map(|d| d.is_open).unwrap_or(false);
+      let position = world.position.get_right(&target_id);
+      let is_obstructed = position
+        .map(|p| find_blocker(world, target_id, *p).is_some())
+        .unwrap_or(false);
+      if is_open && is_obstructed {
+        return Err(ActivityFailure::DoorObstructed);
+      }
+      Ok(())
+    }
+    Interaction::Lever
+    | Interaction::Sign(_)
+    | Interaction::Chest
+    | Interaction::Talk(_)
+    | Interaction::Stairs(_) => Ok(()),
+  }
+}
+
+pub fn update_interaction(world: &mut World, id: Id, target_id: Id) {
+  update_linked_interaction(world, id, target_id, &mut HashSet::new());
+}
+
+fn update_linked_interaction(
+  world: &mut World,
+  id: Id,
+  target_id: Id,
+  visited: &mut HashSet<Id>,
+) {
+  if !visited.insert(target_id) {
+    return;
+  }
+  let Some(interaction) = world.interaction.get(&target_id).copied() else {
+    return;
+  };
+  if validate_interaction(world, target_id).is_err() {
+    return;
+  }
+  let is_player = id == world.view_target;
+  match interaction {
+    Interaction::Door => {
+      let is_open = world.door.get(&target_id).map(|d| d.is_open).unwrap_or(false);
+      update_door(world, target_id, !is_open);
+    }
+    Interaction::Lever => {
+      let Some(lever) = world.lever.get_mut(&target_id) else {
+        return;
+      };
+      lever.is_pulled = !lever.is_pulled;
+      let icon = if lever.is_pulled { '\\' } else { '/' };
+      world.icon.insert(target_id, icon);
+      let linked_ids = world
+        .triggered_by
+        .get_lefts(&target_id)
+        .into_iter()
+        .flat_map(|ids| ids.iter().copied())
+        .collect::<Vec<_>>();
+      for linked_id in linked_ids {
+        update_linked_interaction(world, target_id, linked_id, visited);
+      }
+      if is_player {
+        world.message("you pull the lever");
+      }
+    }
+    Interaction::Sign(text) => {
+      if is_player {
+        world.message(format!("the sign reads: {text}"));
+      }
+    }
+    Interaction::Chest => {
+      let contents = world
+        .held_by
+        .get_lefts(&target_id)
+        .into_iter()
+        .flat_map(|ids| ids.iter().copied())
+        .collect::<Vec<_>>();
+      world.icon.insert(target_id, '_');
+      if contents.is_empty() && is_player {
+        world.message("the chest is empty");
+      }
+      for item_id in contents {
+        world.held_by.insert(item_id, id);
+        if is_player {
+          let name = world.name.get(&item_id).copied().unwrap_or("something");
+          world.message(format!("you find a {}", name.to_lowercase()));
+        }
+      }
+    }
+    Interaction::Talk(text) => {
+      if is_player {
+        let name = world.name.get(&target_id).copied().unwrap_or("someone");
+        world.message(format!("{name}: {text}"));
+      }
+    }
+    Interaction::Stairs(delta) => {
+      update_travel(world, id, delta);
+    }
+  }
+}
@@ -0,0 +1,81 @@
+use crate::*;
+
+pub fn worn_items(world: &World, id: Id) -> impl Iterator<Item = &Id> {
+  world
+    .worn_by
+    .get_lefts(&id)
+    .into_iter()
+    .flat_map(|ids| ids.iter())
+}
+
+pub fn equipment(world: &World, id: Id) -> impl Iterator<Item = Id> + '_ {
+  std::iter::once(id)
+    .chain(held_items(world, id).copied())
+    .chain(worn_items(world, id).copied())
+}
+
+pub fn equipment_stat(world: &World, id: Id, stat: &HasOne<Id, i32>) -> i32 {
+  equipment(world, id).filter_map(|id| stat.get(&id)).sum()
+}
+
+pub fn attack_stat(world: &World, id: Id, source_id: Id, stat: &HasOne<Id, i32>) -> i32 {
+  let own = std::iter::once(id)
+    .chain(worn_items(world, id).copied())
+    .filter_map(|id| stat.get(&id))
+    .sum::<i32>();
+  if source_id == id {
+    own
+  } else {
+    own + item_stat(world, source_id, stat)
+  }
+}
+
+pub fn item_stat(world: &World, id: Id, stat: &HasOne<Id, i32>) -> i32 {
+  item_parts(world, id)
+    .iter()
+    .filter_map(|id| stat.get(id))
+    .sum()
+}
+
+pub fn item_components(world: &World, id: Id) -> impl Iterator<Item = &Id> {
+  world
+    .components
+    .get_lefts(&id)
+    .into_iter()
+    .flat_map(|ids| ids.iter())
+}
+
+pub fn item_parts(world: &World, id: Id) -> Vec<Id> {
+  let mut parts = vec![id];
+  let mut index = 0;
+  while let Some(part) = parts.get(index) {
+    let children = item_components(world, *part)
+      .filter(|child| !parts.contains(child))
+      .copied()
+      .collect::<Vec<_>>();
+    parts.extend(children);
+    index += 1;
+  }
+  parts
+}
+
+pub fn item_damage(world: &World, id: Id) -> i32 {
+  item_stat(world, id, &world.damage)
+}
+
+pub fn update_item_wear(world: &mut World, id: Id) {
+  let mut is_broken = false;
+  for part in item_parts(world, id) {
+    let Some(durability) = world.durability.get_mut(&part) else {
+      continue;
+    };
+    *durability = (*durability - 1).max(0);
+    if *durability == 0 {
+      is_broken = true;
+    }
+  }
+  if is_broken {
+    log!("ITEM", "item broke", id, world.name.get(&id));
+    world.provides_activity.remove_by_left(&id);
+  }
+}
@@ -1,16 +1,32 @@
 pub mod action;
 pub mod activity;
 pub mod ai;
+pub mod behavior;
 pub mod controls;
+pub mod damage;
+pub mod door;
+pub mod dungeon;
+pub mod effect;
 pub mod event;
+pub mod faction;
 pub mod grid;
 pub mod id;
 pub mod input;
+pub mod interaction;
+pub mod item;
 pub mod layout;
 pub mod logging;
 pub mod mapping;
+pub mod memory;
 pub mod navigation;
+pub mod noise;
+pub mod pathfinding;
+pub mod planner;
+pub mod progression;
+pub mod random;
 pub mod relations;
+pub mod resource;
+pub mod spawn;
 pub mod terminal;
 pub mod timeline;
 pub mod turn;
@@ -21,14 +37,30 @@ pub mod world;
 pub use action::*;
 pub use activity::*;
 pub use ai::*;
+pub use behavior::*;
 pub use controls::*;
+pub use damage::*;
+pub use door::*;
+pub use dungeon::*;
+pub use effect::*;
 pub use event::*;
+pub use faction::*;
 pub use grid::*;
 pub use id::Id;
 pub use input::Input;
+pub use interaction::*;
+pub use item::*;
 pub use layout::*;
+pub use memory::*;
 pub use navigation::*;
+pub use noise::*;
+pub use pathfinding::*;
+pub use planner::*;
+pub use progression::*;
+pub use random::*;
 pub use relations::*;
+pub use resource::*;
+pub use spawn::*;
 pub use terminal::Terminal;
 pub use timeline::Timeline;
 pub use turn::TurnType;
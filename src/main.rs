@@ -1,27 +1,58 @@
 use rust_like::*;
+use std::collections::HashMap;
 use std::rc::Rc;
 
+const BEHAVIORS_PATH: &str = "data/behaviors.txt";
+
+fn load_behavior_trees() -> HashMap<String, Rc<BehaviorTree>> {
+  let source = match std::fs::read_to_string(BEHAVIORS_PATH) {
+    Ok(source) => source,
+    Err(error) => {
+      eprintln!("{BEHAVIORS_PATH}: {error}");
+      std::process::exit(1);
+    }
+  };
+  match parse_behavior_trees(&source) {
+    Ok(trees) => trees,
+    Err(error) => {
+      eprintln!("{BEHAVIORS_PATH}: {error}");
+      std::process::exit(1);
+    }
+  }
+}
+
 fn main() {
+  let behavior_trees = load_behavior_trees();
   let mut terminal = Terminal::new().unwrap();
   let visibility_cache = Rc::new(VisibilityCache::new(100));
-  let mut world = World::default();
+  let seed = 41;
+  let mut world = World {
+    random: Random::new(seed),
+    dungeon: Dungeon::new(seed),
+    ai_visibility: Some(Rc::new(VisibilityCache::new(8))),
+    ..Default::default()
+  };
 
 
   terminal.set_str((0, 0),"generating dungeon...");
   terminal.present().unwrap();
 
+  world.navigation.diagonal_cost = Some(1);
+  world.behavior_trees = behavior_trees;
+
   instrument!("mapping", {
     //mapping::arena(&mut world);
-    mapping::cavern(&mut world, 41, 10000, 10000);
+    generate_level(&mut world, 0);
   });
 
-  let player = {
+  {
     let id = world.view_target;
     world.name.insert(id, "Player");
     world.icon.insert(id, '@');
     world.layer.insert(id, Layer::Mob);
     world.position.insert(id, (0, 0));
     world.solidity.insert(id);
+    world.faction.insert(id, Faction::Player);
     world.controls.insert(
       id,
       Controls {
@@ -30,11 +61,17 @@ fn main() {
         act_left: 'h',
         act_right: 'l',
         act_center: 'g',
-        activity_previous: 'y',
-        activity_next: 'u',
+        act_up_left: Some('y'),
+        act_up_right: Some('u'),
+        act_down_left: Some('b'),
+        act_down_right: Some('n'),
+        activity_previous: '[',
+        activity_next: ']',
+        travel_down: Some('>'),
       },
     );
     world.health.insert(id, 3);
+    world.max_health.insert(id, 3);
     world
       .fov
       .insert(id, FieldOfView::new(visibility_cache.clone()));
@@ -44,6 +81,7 @@ fn main() {
       Activity {
         name: "Walk",
         speed: 5,
+        cost: None,
         activity_type: ActivityType::Step(),
       },
     );
@@ -52,67 +90,305 @@ fn main() {
       Activity {
         name: "Hold",
         speed: 3,
+        cost: None,
         activity_type: ActivityType::Wait(),
       },
     );
+    world.provides_activity.insert(
+      id,
+      Activity {
+        name: "Interact",
+        speed: 3,
+        cost: None,
+        activity_type: ActivityType::Interact(),
+      },
+    );
+    world.provides_activity.insert(
+      id,
+      Activity {
+        name: "Grab",
+        speed: 3,
+        cost: None,
+        activity_type: ActivityType::PickUp(),
+      },
+    );
+    world.provides_activity.insert(
+      id,
+      Activity {
+        name: "Shove",
+        speed: 5,
+        cost: Some((Resource::Stamina, 1)),
+        activity_type: ActivityType::Shove(2),
+      },
+    );
+    world.provides_activity.insert(
+      id,
+      Activity {
+        name: "Burning Hands",
+        speed: 6,
+        cost: Some((Resource::Mana, 2)),
+        activity_type: ActivityType::MeleeAttack(2, 3, DamageType::Fire),
+      },
+    );
+    world.stamina.insert(id, Pool::new(6, 10));
+    world.mana.insert(id, Pool::new(4, 25));
+    world.experience.insert(id, Experience::new());
+    world.upgrades.insert(
+      id,
+      Upgrade {
+        name: "Toughness",
+        level: 2,
+        upgrade_type: UpgradeType::MaxHealth(2),
+      },
+    );
+    world.upgrades.insert(
+      id,
+      Upgrade {
+        name: "Endurance",
+        level: 2,
+        upgrade_type: UpgradeType::MaxPool(Resource::Stamina, 3),
+      },
+    );
+    world.upgrades.insert(
+      id,
+      Upgrade {
+        name: "Focus",
+        level: 2,
+        upgrade_type: UpgradeType::MaxPool(Resource::Mana, 3),
+      },
+    );
+    world.upgrades.insert(
+      id,
+      Upgrade {
+        name: "Heave",
+        level: 2,
+        upgrade_type: UpgradeType::Learn(Activity {
+          name: "Heave",
+          speed: 6,
+          cost: Some((Resource::Stamina, 2)),
+          activity_type: ActivityType::Shove(3),
+        }),
+      },
+    );
+    world.upgrades.insert(
+      id,
+      Upgrade {
+        name: "Fireball",
+        level: 3,
+        upgrade_type: UpgradeType::Learn(Activity {
+          name: "Fireball",
+          speed: 8,
+          cost: Some((Resource::Mana, 5)),
+          activity_type: ActivityType::MeleeAttack(3, 5, DamageType::Fire),
+        }),
+      },
+    );
     let sword = {
       let id = Id::new();
       world.name.insert(id, "Arming Sword");
       world.icon.insert(id, '/');
-      world.layer.insert(id, Layer::Mob);
+      world.layer.insert(id, Layer::Item);
       world.provides_activity.insert(
         id,
         Activity {
           name: "Stab",
           speed: 5,
-          activity_type: ActivityType::MeleeAttack(1),
+          cost: None,
+          activity_type: ActivityType::MeleeAttack(1, 2, DamageType::Slashing),
+        },
+      );
+      world.provides_activity.insert(
+        id,
+        Activity {
+          name: "Cleave",
+          speed: 8,
+          cost: Some((Resource::Stamina, 3)),
+          activity_type: ActivityType::MeleeAttack(2, 4, DamageType::Slashing),
         },
       );
+      world.durability.insert(id, 30);
+      world.accuracy.insert(id, 10);
       id
     };
     world.held_by.insert(sword, id);
-    id
-  };
-
-  let mut goblin = |i, p| {
-    let id = Id::new();
-    world.name.insert(id, "Goblin");
-    world.icon.insert(id, i);
-    world.layer.insert(id, Layer::Mob);
-    world.position.insert(id, p);
-    world.solidity.insert(id);
-    world.ai.insert(id, Ai { target: player });
-    world.health.insert(id, 1);
-    world.timeline.push(0, turn::Ai::new_turn(id));
-    world.provides_activity.insert(
-      id,
-      Activity {
-        name: "Walk",
-        speed: 10,
-        activity_type: ActivityType::Step(),
-      },
-    );
-    let club = {
+    let spear = {
+      let id = Id::new();
+      world.name.insert(id, "Spear");
+      world.icon.insert(id, '|');
+      world.layer.insert(id, Layer::Item);
+      world.provides_activity.insert(
+        id,
+        Activity {
+          name: "Thrust",
+          speed: 7,
+          cost: None,
+          activity_type: ActivityType::MeleeAttack(0, 1, DamageType::Slashing),
+        },
+      );
+      let shaft = Id::new();
+      world.name.insert(shaft, "Ash Shaft");
+      world.durability.insert(shaft, 12);
+      world.components.insert(shaft, id);
+      let head = Id::new();
+      world.name.insert(head, "Iron Spearhead");
+      world.damage.insert(head, 2);
+      world.durability.insert(head, 20);
+      world.critical.insert(head, 10);
+      world.components.insert(head, id);
+      id
+    };
+    world.held_by.insert(spear, id);
+    let torch = {
       let id = Id::new();
-      world.name.insert(id, "Crude Club");
+      world.name.insert(id, "Torch");
+      world.icon.insert(id, '~');
+      world.layer.insert(id, Layer::Item);
+      world.provides_activity.insert(
+        id,
+        Activity {
+          name: "Singe",
+          speed: 6,
+          cost: None,
+          activity_type: ActivityType::MeleeAttack(1, 1, DamageType::Fire),
+        },
+      );
+      world.durability.insert(id, 15);
+      id
+    };
+    world.held_by.insert(torch, id);
+    let jerkin = {
+      let id = Id::new();
+      world.name.insert(id, "Leather Jerkin");
+      world.icon.insert(id, '[');
+      world.layer.insert(id, Layer::Item);
+      world.damage_modifiers.insert(id, DamageModifier::Armor(1));
+      id
+    };
+    world.worn_by.insert(jerkin, id);
+    let potion = {
+      let id = Id::new();
+      world.name.insert(id, "Healing Potion");
       world.icon.insert(id, '!');
-      world.layer.insert(id, Layer::Mob);
+      world.layer.insert(id, Layer::Item);
       world.provides_activity.insert(
         id,
         Activity {
-          name: "Wallop",
-          speed: 10,
-          activity_type: ActivityType::MeleeAttack(1),
+          name: "Quaff",
+          speed: 3,
+          cost: None,
+          activity_type: ActivityType::Use(Effect::Heal(2)),
         },
       );
       id
     };
-    world.held_by.insert(club, id);
-    id
-  };
-  _ = goblin('G', (8, 3));
-  _ = goblin('N', (9, 2));
-  _ = goblin('T', (8, 5));
+    world.held_by.insert(potion, id);
+    let stoneskin = {
+      let id = Id::new();
+      world.name.insert(id, "Stoneskin Draught");
+      world.icon.insert(id, '!');
+      world.layer.insert(id, Layer::Item);
+      world.provides_activity.insert(
+        id,
+        Activity {
+          name: "Quaff",
+          speed: 3,
+          cost: None,
+          activity_type: ActivityType::Use(Effect::Buff(DamageModifier::Armor(2), 60)),
+        },
+      );
+      id
+    };
+    world.held_by.insert(stoneskin, id);
+  }
+
+  {
+    let id = Id::new();
+    world.name.insert(id, "sign");
+    world.icon.insert(id, '?');
+    world.layer.insert(id, Layer::Feature);
+    world.position.insert(id, (-2, -2));
+    world
+      .interaction
+      .insert(id, Interaction::Sign("beware of goblins"));
+  }
+
+  {
+    let id = Id::new();
+    world.name.insert(id, "chest");
+    world.icon.insert(id, '=');
+    world.layer.insert(id, Layer::Feature);
+    world.position.insert(id, (3, -3));
+    world.solidity.insert(id);
+    world.interaction.insert(id, Interaction::Chest);
+    let axe = Id::new();
+    world.name.insert(axe, "Hand Axe");
+    world.icon.insert(axe, '/');
+    world.layer.insert(axe, Layer::Item);
+    world.provides_activity.insert(
+      axe,
+      Activity {
+        name: "Chop",
+        speed: 6,
+        cost: None,
+        activity_type: ActivityType::MeleeAttack(1, 3, DamageType::Slashing),
+      },
+    );
+    world.durability.insert(axe, 12);
+    world.held_by.insert(axe, id);
+    let wand = Id::new();
+    world.name.insert(wand, "Wand of Mending");
+    world.icon.insert(wand, '-');
+    world.layer.insert(wand, Layer::Item);
+    world.provides_activity.insert(
+      wand,
+      Activity {
+        name: "Mend",
+        speed: 5,
+        cost: None,
+        activity_type: ActivityType::Use(Effect::Heal(1)),
+      },
+    );
+    world.charges.insert(wand, 3);
+    world.held_by.insert(wand, id);
+  }
+
+  {
+    let id = Id::new();
+    world.name.insert(id, "lever");
+    world.icon.insert(id, '/');
+    world.layer.insert(id, Layer::Feature);
+    world.position.insert(id, (-3, 3));
+    world.interaction.insert(id, Interaction::Lever);
+    world.lever.insert(id, Lever { is_pulled: false });
+    let nearest_door = world
+      .door
+      .iter()
+      .filter_map(|(door_id, _)| world.position.get_right(door_id).map(|p| (*door_id, *p)))
+      .min_by_key(|(_, p)| p.0.pow(2) + p.1.pow(2));
+    if let Some((door_id, _)) = nearest_door {
+      world.triggered_by.insert(door_id, id);
+    }
+  }
+
+  {
+    let id = Id::new();
+    world.name.insert(id, "Hermit");
+    world.icon.insert(id, 'h');
+    world.layer.insert(id, Layer::Mob);
+    world.position.insert(id, (-5, 0));
+    world.solidity.insert(id);
+    world.faction.insert(id, Faction::Villager);
+    world
+      .interaction
+      .insert(id, Interaction::Talk("the goblins came from below"));
+  }
+
+  let brute = spawn_goblin(&mut world, 'G', (8, 3));
+  attach_behavior(&mut world, brute, "brute");
+  let skirmisher = spawn_goblin(&mut world, 'N', (9, 2));
+  attach_behavior(&mut world, skirmisher, "skirmisher");
+  spawn_goblin(&mut world, 'T', (8, 5));
+  spawn_goblin_archer(&mut world, 'A', (12, 6));
 
   world.startup();
 
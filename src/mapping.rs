@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::*;
 
 pub fn arena(world: &mut World) {
@@ -38,25 +38,31 @@ pub fn arena(world: &mut World) {
   pillar((4, 9));
 }
 
-pub fn cavern(world: &mut World, seed: u32, walkers: usize, max_steps: usize) {
+pub fn cavern(
+  world: &mut World,
+  seed: u32,
+  walkers: usize,
+  max_steps: usize,
+) -> Vec<(i32, i32)> {
   let mut random = Random::new(seed);
+  let points = grid::spiral((0, 0), 100).collect::<Vec<_>>();
   let mut cells = HashMap::new();
-  for position in grid::spiral((0, 0), 100) {
-    cells.insert(position, false);
+  for position in points.iter() {
+    cells.insert(*position, false);
   }
   let rooms = [
     (0, 0),
     {
-      let start_index = random.range(0, cells.len() as i32) as usize;
-      cells.keys().nth(start_index).cloned().unwrap()
+      let start_index = random.range(0, points.len() as i32) as usize;
+      points[start_index]
     },
     {
-      let start_index = random.range(0, cells.len() as i32) as usize;
-      cells.keys().nth(start_index).cloned().unwrap()
+      let start_index = random.range(0, points.len() as i32) as usize;
+      points[start_index]
     },
     {
-      let start_index = random.range(0, cells.len() as i32) as usize;
-      cells.keys().nth(start_index).cloned().unwrap()
+      let start_index = random.range(0, points.len() as i32) as usize;
+      points[start_index]
     },
   ];
   for center in rooms {
@@ -70,8 +76,8 @@ pub fn cavern(world: &mut World, seed: u32, walkers: usize, max_steps: usize) {
     }
   }
   for _ in 0..walkers {
-    let start_index = random.range(0, cells.len() as i32) as usize;
-    let mut position = cells.keys().nth(start_index).cloned().unwrap();
+    let start_index = random.range(0, points.len() as i32) as usize;
+    let mut position = points[start_index];
     for _ in 0..max_steps {
       let next_position = match (random.bool(), random.bool()) {
         (true, true)   => (position.0 - 1, position.1    ),
@@ -86,6 +92,12 @@ pub fn cavern(world: &mut World, seed: u32, walkers: usize, max_steps: usize) {
       position = next_position;
     }
   }
+  let doors = find_room_entrances(&cells, &rooms);
+  let mut open_cells = points
+    .iter()
+    .filter(|p| cells.get(p).copied().unwrap_or(false))
+    .copied()
+    .collect::<Vec<_>>();
   for (position, is_open) in cells {
     let id = Id::new();
     world.position.insert(id, position);
@@ -101,35 +113,50 @@ pub fn cavern(world: &mut World, seed: u32, walkers: usize, max_steps: usize) {
       world.opacity.insert(id);
     }
   }
-}
-
-struct Random(u32);
-
-impl Random {
-  pub fn new(seed: u32) -> Self {
-    Self(seed)
-  }
-
-  pub fn bool(&mut self) -> bool {
-    self.0 = hash_u32(self.0);
-    self.0 % 2 == 0
-  }
-
-  pub fn range(&mut self, a: i32, b: i32) -> i32 {
-    self.0 = hash_u32(self.0);
-    let v = self.0 as f32;
-    let t = v / (u32::MAX as f32);
-    let d = t * (b as f32 - a as f32);
-    a + d as i32
+  for position in doors.iter() {
+    spawn_door(world, *position, false);
   }
+  open_cells.retain(|p| !doors.contains(p));
+  open_cells
 }
 
-pub const fn hash_u32(x: u32) -> u32 {
-  let mut v = x;
-  v ^= v.wrapping_shr(16);
-  v = v.wrapping_mul(0x85ebca6b);
-  v ^= v.wrapping_shr(13);
-  v = v.wrapping_mul(0xc2b2ae35);
-  v ^= v.wrapping_shr(16);
-  v
+fn find_room_entrances(cells: &HashMap<(i32, i32), bool>, rooms: &[(i32, i32)]) -> Vec<(i32, i32)> {
+  let is_open = |position: &(i32, i32)| cells.get(position).copied().unwrap_or(false);
+  let in_room = |position: &(i32, i32)| {
+    rooms.iter().any(|center| {
+      let delta = (position.0 - center.0, position.1 - center.1);
+      let distance = ((delta.0.pow(2) + delta.1.pow(2)) as f32).sqrt();
+      distance < 10.0
+    })
+  };
+  let mut candidates = cells
+    .keys()
+    .filter(|position| is_open(position) && !in_room(position))
+    .copied()
+    .collect::<Vec<_>>();
+  candidates.sort();
+  let mut doors = HashSet::new();
+  for position in candidates {
+    let horizontal = [(position.0 - 1, position.1), (position.0 + 1, position.1)];
+    let vertical = [(position.0, position.1 - 1), (position.0, position.1 + 1)];
+    let is_corridor = |open: &[(i32, i32); 2], closed: &[(i32, i32); 2]| {
+      open.iter().all(is_open) && !closed.iter().any(is_open)
+    };
+    let passage = if is_corridor(&horizontal, &vertical) {
+      horizontal
+    } else if is_corridor(&vertical, &horizontal) {
+      vertical
+    } else {
+      continue;
+    };
+    if !passage.iter().any(in_room) {
+      continue;
+    }
+    let neighbors = horizontal.iter().chain(vertical.iter());
+    if neighbors.into_iter().any(|n| doors.contains(n)) {
+      continue;
+    }
+    doors.insert(position);
+  }
+  doors.into_iter().collect()
 }
@@ -0,0 +1,154 @@
+use crate::*;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sighting {
+  pub position: Point,
+  pub time: usize,
+}
+
+#[derive(Debug)]
+pub struct Memory {
+  pub duration: usize,
+  pub post: Option<Point>,
+  pub targets: HashMap<Id, Sighting>,
+  pub doors: HashMap<Id, (Sighting, bool)>,
+  pub items: HashMap<Id, Sighting>,
+}
+
+impl Memory {
+  pub fn new(duration: usize) -> Self {
+    Self {
+      duration,
+      post: None,
+      targets: HashMap::new(),
+      doors: HashMap::new(),
+      items: HashMap::new(),
+    }
+  }
+
+  pub fn with_post(self, post: Point) -> Self {
+    Self {
+      post: Some(post),
+      ..self
+    }
+  }
+
+  pub fn last_seen(&self, id: Id) -> Option<Sighting> {
+    self.targets.get(&id).copied()
+  }
+
+  pub fn is_closed_door_at(&self, position: Point) -> bool {
+    self
+      .doors
+      .values()
+      .any(|(sighting, is_open)| sighting.position == position && !is_open)
+  }
+
+  pub fn is_item_at(&self, position: Point) -> bool {
+    self.items.values().any(|sighting| sighting.position == position)
+  }
+
+  pub fn delay(&mut self, elapsed: usize) {
+    let sightings = self
+      .targets
+      .values_mut()
+      .chain(self.doors.values_mut().map(|(sighting, _)| sighting))
+      .chain(self.items.values_mut());
+    for sighting in sightings {
+      sighting.time += elapsed;
+    }
+  }
+
+  fn forget(&mut self, time: usize) {
+    let is_fresh = |sighting: &Sighting| sighting.time + self.duration > time;
+    self.targets.retain(|_, sighting| is_fresh(sighting));
+    self.doors.retain(|_, (sighting, _)| is_fresh(sighting));
+    self.items.retain(|_, sighting| is_fresh(sighting));
+  }
+}
+
+pub fn knows_closed_door_at(world: &World, id: Id, position: Point) -> bool {
+  match world.memory.get(&id) {
+    Some(memory) => memory.is_closed_door_at(position),
+    None => is_closed_door_at(world, position),
+  }
+}
+
+pub fn knows_item_at(world: &World, id: Id, position: Point) -> bool {
+  match world.memory.get(&id) {
+    Some(memory) => memory.is_item_at(position),
+    None => find_item(world, position).is_some(),
+  }
+}
+
+enum Observation {
+  Door(bool),
+  Item,
+  Creature,
+}
+
+fn observe(world: &World, id: Id) -> Option<Observation> {
+  if let Some(door) = world.door.get(&id) {
+    return Some(Observation::Door(door.is_open));
+  }
+  if world.layer.get(&id) == Some(&Layer::Item) {
+    return Some(Observation::Item);
+  }
+  if world.health.contains_key(&id) && world.faction.contains_key(&id) {
+    return Some(Observation::Creature);
+  }
+  None
+}
+
+pub fn update_memories(world: &mut World) {
+  let ids = world.memory.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+  let time = world.time;
+  for id in ids {
+    let visible = visible_positions(world, id);
+    let observations = seen_entities(world, &visible)
+      .into_iter()
+      .filter(|(seen_id, _)| *seen_id != id)
+      .filter_map(|(seen_id, position)| {
+        let sighting = Sighting { position, time };
+        observe(world, seen_id).map(|observation| (seen_id, sighting, observation))
+      })
+      .collect::<Vec<_>>();
+    let Some(memory) = world.memory.get_mut(&id) else {
+      continue;
+    };
+    memory.items.retain(|_, sighting| !visible.contains(&sighting.position));
+    for (seen_id, sighting, observation) in observations {
+      match observation {
+        Observation::Door(is_open) => {
+          memory.doors.insert(seen_id, (sighting, is_open));
+        }
+        Observation::Item => {
+          memory.items.insert(seen_id, sighting);
+        }
+        Observation::Creature => {
+          memory.targets.insert(seen_id, sighting);
+        }
+      }
+    }
+    memory.forget(time);
+  }
+}
+
+fn visible_positions(world: &World, id: Id) -> HashSet<Point> {
+  let (Some(fov), Some(position)) = (world.fov.get(&id), world.position.get_right(&id)) else {
+    return HashSet::new();
+  };
+  fov
+    .visible_points()
+    .map(|p| (position.0 + p.0, position.1 + p.1))
+    .collect()
+}
+
+fn seen_entities(world: &World, visible: &HashSet<Point>) -> Vec<(Id, Point)> {
+  visible
+    .iter()
+    .filter_map(|p| world.position.get_lefts(p).map(|ids| (*p, ids)))
+    .flat_map(|(p, ids)| ids.iter().map(move |seen_id| (*seen_id, p)))
+    .collect()
+}
@@ -1,12 +1,25 @@
-use crate::grid::Point;
-use std::collections::HashMap;
+use crate::grid::{Point, DIAGONAL_DIRECTIONS, ORTHOGONAL_DIRECTIONS};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 
-#[derive(Default)]
+const FLEE_MULTIPLIER: (usize, usize) = (6, 5);
+
+#[derive(Default, Clone)]
 pub struct Navigation {
   cells: HashMap<Point, usize>,
+  pub diagonal_cost: Option<usize>,
+  pub cut_corners: bool,
 }
 
 impl Navigation {
+  pub fn take_cells(&mut self) -> Self {
+    Self {
+      cells: std::mem::take(&mut self.cells),
+      diagonal_cost: self.diagonal_cost,
+      cut_corners: self.cut_corners,
+    }
+  }
+
   pub fn reset(&mut self) {
     for (_, cell) in self.cells.iter_mut() {
       *cell = usize::MAX;
@@ -26,36 +39,148 @@ impl Navigation {
   }
 
   pub fn calculate(&mut self) {
-    let mut changed = true;
-    while changed {
-      changed = false;
-      let points = self.cells.keys().copied().collect::<Vec<_>>();
-      for point in points {
-        let Some((_, smallest_value)) = self.best_neighbor(point) else {
-          continue;
-        };
-        let Some(value) = self.cells.get_mut(&point) else {
+    let mut frontier = self
+      .cells
+      .iter()
+      .filter(|(_, value)| **value != usize::MAX)
+      .map(|(point, value)| Reverse((*value, *point)))
+      .collect::<BinaryHeap<_>>();
+    while let Some(Reverse((value, point))) = frontier.pop() {
+      if self.get_value(point) != Some(value) {
+        continue;
+      }
+      for (neighbor, cost) in self.neighbors(point) {
+        let next_value = value.saturating_add(cost);
+        let Some(neighbor_value) = self.cells.get_mut(&neighbor) else {
           continue;
         };
-        if smallest_value < value.saturating_sub(1) {
-          changed = true;
-          *value = smallest_value.saturating_add(1);
+        if next_value < *neighbor_value {
+          *neighbor_value = next_value;
+          frontier.push(Reverse((next_value, neighbor)));
         }
       }
     }
   }
 
+  pub fn flee_map(&self) -> Self {
+    let (numerator, denominator) = FLEE_MULTIPLIER;
+    let Some(maximum) = self.cells.values().filter(|v| **v != usize::MAX).max() else {
+      return self.clone();
+    };
+    let mut flee_map = self.clone();
+    for (_, value) in flee_map.cells.iter_mut() {
+      if *value != usize::MAX {
+        *value = (maximum - *value) * numerator / denominator;
+      }
+    }
+    flee_map.calculate();
+    flee_map
+  }
+
   pub fn best_neighbor(&self, point: Point) -> Option<(Point, usize)> {
-    let neighbors = [
-      (point.0 - 1, point.1),
-      (point.0 + 1, point.1),
-      (point.0, point.1 - 1),
-      (point.0, point.1 + 1),
-    ];
-    neighbors
+    self
+      .neighbors(point)
       .into_iter()
-      .filter_map(|p| self.cells.get(&p).map(|v| (p, *v)))
-      .filter(|(_, v)| *v != usize::MAX)
-      .min_by(|a, b| a.1.cmp(&b.1))
+      .filter_map(|(p, cost)| self.get_finite_value(p).map(|v| (p, v, cost)))
+      .min_by_key(|(_, v, cost)| v.saturating_add(*cost))
+      .map(|(p, v, _)| (p, v))
+  }
+
+  pub fn neighbors(&self, point: Point) -> Vec<(Point, usize)> {
+    let offset = |d: Point| (point.0 + d.0, point.1 + d.1);
+    let mut neighbors = ORTHOGONAL_DIRECTIONS
+      .into_iter()
+      .map(|d| (offset(d), 1))
+      .collect::<Vec<_>>();
+    if let Some(diagonal_cost) = self.diagonal_cost {
+      for d in DIAGONAL_DIRECTIONS {
+        if !self.allows_step(point, d) {
+          continue;
+        }
+        neighbors.push((offset(d), diagonal_cost));
+      }
+    }
+    neighbors
+  }
+
+  pub fn allows_step(&self, point: Point, direction: Point) -> bool {
+    if self.cut_corners || direction.0 == 0 || direction.1 == 0 {
+      return true;
+    }
+    self.cells.contains_key(&(point.0 + direction.0, point.1))
+      && self.cells.contains_key(&(point.0, point.1 + direction.1))
+  }
+
+  pub fn get_finite_value(&self, point: Point) -> Option<usize> {
+    self.get_value(point).filter(|v| *v != usize::MAX)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn open_area(width: i32, height: i32) -> Navigation {
+    let mut navigation = Navigation::default();
+    for x in 0..width {
+      for y in 0..height {
+        navigation.set_value((x, y), usize::MAX);
+      }
+    }
+    navigation
+  }
+
+  #[test]
+  fn calculate_measures_distance_from_seeds() {
+    let mut navigation = open_area(5, 5);
+    navigation.set_value((0, 0), 0);
+    navigation.calculate();
+    assert_eq!(navigation.get_value((4, 4)), Some(8));
+    navigation.diagonal_cost = Some(1);
+    navigation.reset();
+    navigation.set_value((0, 0), 0);
+    navigation.calculate();
+    assert_eq!(navigation.get_value((4, 4)), Some(4));
+  }
+
+  #[test]
+  fn calculate_leaves_unreachable_cells_infinite() {
+    let mut navigation = open_area(3, 1);
+    navigation.set_value((5, 0), usize::MAX);
+    navigation.set_value((0, 0), 0);
+    navigation.calculate();
+    assert_eq!(navigation.get_value((2, 0)), Some(2));
+    assert_eq!(navigation.get_value((5, 0)), Some(usize::MAX));
+    assert_eq!(navigation.get_finite_value((5, 0)), None);
+  }
+
+  #[test]
+  fn flee_map_leads_away_from_the_goal() {
+    let mut navigation = open_area(7, 1);
+    navigation.set_value((0, 0), 0);
+    navigation.calculate();
+    let flee_map = navigation.flee_map();
+    assert_eq!(flee_map.best_neighbor((3, 0)).map(|(p, _)| p), Some((4, 0)));
+    assert_eq!(flee_map.get_value((6, 0)), Some(0));
+  }
+
+  #[test]
+  fn flee_map_prefers_open_corridor_over_dead_end() {
+    let mut navigation = open_area(16, 1);
+    navigation.set_value((3, 1), usize::MAX);
+    navigation.set_value((0, 0), 0);
+    navigation.calculate();
+    let flee_map = navigation.flee_map();
+    assert_eq!(flee_map.best_neighbor((3, 0)).map(|(p, _)| p), Some((4, 0)));
+  }
+
+  #[test]
+  fn diagonal_steps_cannot_cut_wall_corners() {
+    let mut navigation = open_area(2, 2);
+    navigation.remove_point((1, 0));
+    assert!(navigation.allows_step((0, 0), (0, 1)));
+    assert!(!navigation.allows_step((0, 0), (1, 1)));
+    navigation.cut_corners = true;
+    assert!(navigation.allows_step((0, 0), (1, 1)));
   }
 }
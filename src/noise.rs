@@ -0,0 +1,55 @@
+use crate::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+const DOOR_ATTENUATION: usize = 4;
+
+pub fn propagate_noise(world: &World, origin: Point, loudness: usize) -> HashMap<Point, usize> {
+  let mut heard = HashMap::new();
+  if loudness == 0 || world.navigation.get_value(origin).is_none() {
+    return heard;
+  }
+  let mut frontier = BinaryHeap::new();
+  frontier.push(Reverse((0, origin)));
+  while let Some(Reverse((distance, point))) = frontier.pop() {
+    if distance >= loudness || heard.contains_key(&point) {
+      continue;
+    }
+    heard.insert(point, loudness - distance);
+    for (neighbor, cost) in world.navigation.neighbors(point) {
+      if heard.contains_key(&neighbor) || world.navigation.get_value(neighbor).is_none() {
+        continue;
+      }
+      let cost = if is_closed_door_at(world, neighbor) {
+        cost + DOOR_ATTENUATION
+      } else {
+        cost
+      };
+      frontier.push(Reverse((distance + cost, neighbor)));
+    }
+  }
+  heard
+}
+
+pub fn update_noise(world: &mut World, id: Id, loudness: usize) {
+  let Some(origin) = world.position.get_right(&id).copied() else {
+    return;
+  };
+  let heard = propagate_noise(world, origin, loudness);
+  let listeners = world
+    .ai
+    .iter()
+    .map(|(listener_id, _)| *listener_id)
+    .filter(|listener_id| *listener_id != id && is_hostile(world, *listener_id, id))
+    .filter(|listener_id| {
+      let position = world.position.get_right(listener_id);
+      position.is_some_and(|p| heard.contains_key(p))
+    })
+    .collect::<Vec<_>>();
+  for listener_id in listeners {
+    log!("NOISE", "heard noise", listener_id, id, origin);
+    if let Some(ai) = world.ai.get_mut(&listener_id) {
+      ai.hear(origin);
+    }
+  }
+}
@@ -0,0 +1,266 @@
+use crate::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+const DEFAULT_SEARCH_LIMIT: usize = 4096;
+
+#[derive(Debug, Clone)]
+pub struct Path {
+  points: Vec<Point>,
+  index: usize,
+  pub cost: usize,
+}
+
+impl Path {
+  pub fn goal(&self) -> Option<Point> {
+    self.points.last().copied()
+  }
+
+  pub fn peek(&self) -> Option<Point> {
+    self.points.get(self.index).copied()
+  }
+
+  pub fn remaining(&self) -> usize {
+    self.points.len() - self.index
+  }
+}
+
+impl Iterator for Path {
+  type Item = Point;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let point = self.points.get(self.index).copied()?;
+    self.index += 1;
+    Some(point)
+  }
+}
+
+pub struct Pathfinder<P, C>
+where
+  P: Fn(Point) -> bool,
+  C: Fn(Point, Point) -> usize,
+{
+  pub passable: P,
+  pub cost: C,
+  pub diagonal_cost: Option<usize>,
+  pub cut_corners: bool,
+  pub search_limit: usize,
+}
+
+impl<P, C> Pathfinder<P, C>
+where
+  P: Fn(Point) -> bool,
+  C: Fn(Point, Point) -> usize,
+{
+  pub fn new(passable: P, cost: C) -> Self {
+    Self {
+      passable,
+      cost,
+      diagonal_cost: None,
+      cut_corners: false,
+      search_limit: DEFAULT_SEARCH_LIMIT,
+    }
+  }
+
+  pub fn find(&self, start: Point, goal: Point) -> Option<Path> {
+    let mut frontier = BinaryHeap::new();
+    let mut came_from = HashMap::new();
+    let mut costs = HashMap::new();
+    costs.insert(start, 0);
+    frontier.push(Reverse((self.heuristic(start, goal), start)));
+    let mut searched = 0;
+    while let Some(Reverse((_, point))) = frontier.pop() {
+      if point == goal {
+        return Some(self.reconstruct(&came_from, start, goal, costs[&goal]));
+      }
+      searched += 1;
+      if searched > self.search_limit {
+        return None;
+      }
+      let cost = costs[&point];
+      for (neighbor, multiplier) in self.neighbors(point) {
+        if neighbor != goal && !(self.passable)(neighbor) {
+          continue;
+        }
+        let next_cost = cost + (self.cost)(point, neighbor) * multiplier;
+        if costs.get(&neighbor).is_some_and(|c| *c <= next_cost) {
+          continue;
+        }
+        costs.insert(neighbor, next_cost);
+        came_from.insert(neighbor, point);
+        frontier.push(Reverse((next_cost + self.heuristic(neighbor, goal), neighbor)));
+      }
+    }
+    None
+  }
+
+  fn neighbors(&self, point: Point) -> Vec<(Point, usize)> {
+    let offset = |d: Point| (point.0 + d.0, point.1 + d.1);
+    let mut neighbors = ORTHOGONAL_DIRECTIONS
+      .into_iter()
+      .map(|d| (offset(d), 1))
+      .collect::<Vec<_>>();
+    if let Some(diagonal_cost) = self.diagonal_cost {
+      for d in DIAGONAL_DIRECTIONS {
+        let is_cutting_corner =
+          !(self.passable)(offset((d.0, 0))) || !(self.passable)(offset((0, d.1)));
+        if is_cutting_corner && !self.cut_corners {
+          continue;
+        }
+        neighbors.push((offset(d), diagonal_cost));
+      }
+    }
+    neighbors
+  }
+
+  fn heuristic(&self, a: Point, b: Point) -> usize {
+    let delta = ((a.0 - b.0).unsigned_abs(), (a.1 - b.1).unsigned_abs());
+    let (short, long) = (delta.0.min(delta.1) as usize, delta.0.max(delta.1) as usize);
+    match self.diagonal_cost {
+      Some(diagonal_cost) => short * diagonal_cost.min(2) + (long - short),
+      None => short + long,
+    }
+  }
+
+  fn reconstruct(
+    &self,
+    came_from: &HashMap<Point, Point>,
+    start: Point,
+    goal: Point,
+    cost: usize,
+  ) -> Path {
+    let mut points = vec![goal];
+    let mut current = goal;
+    while let Some(previous) = came_from.get(&current) {
+      if *previous == start {
+        break;
+      }
+      points.push(*previous);
+      current = *previous;
+    }
+    points.reverse();
+    if start == goal {
+      points.clear();
+    }
+    Path {
+      points,
+      index: 0,
+      cost,
+    }
+  }
+}
+
+pub fn is_passable(world: &World, id: Id, point: Point) -> bool {
+  if world.navigation.get_value(point).is_none() {
+    return false;
+  }
+  is_closed_door_at(world, point) || find_blocker(world, id, point).is_none()
+}
+
+pub fn is_explored(world: &World, point: Point) -> bool {
+  world.explored.contains(&point)
+}
+
+pub fn terrain_cost(world: &World, _from: Point, to: Point) -> usize {
+  if is_closed_door_at(world, to) {
+    2
+  } else {
+    1
+  }
+}
+
+pub fn find_travel_path(world: &World, id: Id, goal: Point) -> Option<Path> {
+  let start = *world.position.get_right(&id)?;
+  let passable = |p| is_explored(world, p) && is_passable(world, id, p);
+  let cost = |from, to| terrain_cost(world, from, to);
+  let mut pathfinder = Pathfinder::new(passable, cost);
+  pathfinder.diagonal_cost = world.navigation.diagonal_cost;
+  pathfinder.cut_corners = world.navigation.cut_corners;
+  pathfinder.find(start, goal)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  fn walls(points: &[Point]) -> HashSet<Point> {
+    points.iter().copied().collect()
+  }
+
+  fn in_bounds(p: Point) -> bool {
+    (0..5).contains(&p.0) && (0..5).contains(&p.1)
+  }
+
+  #[test]
+  fn find_walks_straight_on_open_ground() {
+    let pathfinder = Pathfinder::new(in_bounds, |_, _| 1);
+    let path = pathfinder.find((0, 0), (3, 0)).unwrap();
+    assert_eq!(path.cost, 3);
+    assert_eq!(path.collect::<Vec<_>>(), vec![(1, 0), (2, 0), (3, 0)]);
+  }
+
+  #[test]
+  fn find_routes_around_walls() {
+    let walls = walls(&[(2, 0), (2, 1), (2, 2), (2, 3)]);
+    let pathfinder = Pathfinder::new(|p| in_bounds(p) && !walls.contains(&p), |_, _| 1);
+    let path = pathfinder.find((0, 0), (4, 0)).unwrap();
+    assert_eq!(path.cost, 12);
+    assert!(path.clone().any(|p| p == (2, 4)));
+    assert!(path.clone().all(|p| !walls.contains(&p)));
+    assert_eq!(path.goal(), Some((4, 0)));
+  }
+
+  #[test]
+  fn find_fails_when_the_goal_is_walled_off() {
+    let walls = walls(&[(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)]);
+    let pathfinder = Pathfinder::new(|p| in_bounds(p) && !walls.contains(&p), |_, _| 1);
+    assert!(pathfinder.find((0, 0), (4, 0)).is_none());
+  }
+
+  #[test]
+  fn find_cuts_diagonals_when_allowed() {
+    let mut pathfinder = Pathfinder::new(in_bounds, |_, _| 1);
+    assert_eq!(pathfinder.find((0, 0), (3, 3)).unwrap().cost, 6);
+    pathfinder.diagonal_cost = Some(1);
+    let path = pathfinder.find((0, 0), (3, 3)).unwrap();
+    assert_eq!(path.cost, 3);
+    assert_eq!(path.remaining(), 3);
+  }
+
+  #[test]
+  fn find_prices_diagonals_by_their_cost() {
+    let mut pathfinder = Pathfinder::new(in_bounds, |_, _| 1);
+    pathfinder.diagonal_cost = Some(3);
+    assert_eq!(pathfinder.find((0, 0), (1, 1)).unwrap().cost, 2);
+    pathfinder.diagonal_cost = Some(2);
+    assert_eq!(pathfinder.find((0, 0), (3, 2)).unwrap().cost, 5);
+  }
+
+  #[test]
+  fn find_does_not_squeeze_between_walls() {
+    let walls = walls(&[(1, 0), (0, 1)]);
+    let mut pathfinder = Pathfinder::new(|p| in_bounds(p) && !walls.contains(&p), |_, _| 1);
+    pathfinder.diagonal_cost = Some(1);
+    assert!(pathfinder.find((0, 0), (1, 1)).is_none());
+    pathfinder.cut_corners = true;
+    assert_eq!(pathfinder.find((0, 0), (1, 1)).unwrap().cost, 1);
+  }
+
+  #[test]
+  fn find_avoids_expensive_terrain() {
+    let cost = |_: Point, to: Point| if to == (1, 0) { 10 } else { 1 };
+    let pathfinder = Pathfinder::new(in_bounds, cost);
+    let path = pathfinder.find((0, 0), (2, 0)).unwrap();
+    assert_eq!(path.cost, 4);
+    assert!(!path.clone().any(|p| p == (1, 0)));
+  }
+
+  #[test]
+  fn find_returns_an_empty_path_to_the_start() {
+    let pathfinder = Pathfinder::new(in_bounds, |_, _| 1);
+    let mut path = pathfinder.find((1, 1), (1, 1)).unwrap();
+    assert_eq!(path.cost, 0);
+    assert_eq!(path.next(), None);
+  }
+}
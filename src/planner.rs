@@ -0,0 +1,248 @@
+use crate::*;
+
+const PLAN_DIRECTIONS: [(i32, i32); 9] = [
+  (0, 0),
+  (-1, 0),
+  (1, 0),
+  (0, -1),
+  (0, 1),
+  (-1, -1),
+  (1, -1),
+  (-1, 1),
+  (1, 1),
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct Plan {
+  pub source_id: Id,
+  pub activity: Activity,
+  pub direction: (i32, i32),
+  pub score: i32,
+}
+
+impl Plan {
+  pub fn time_cost(&self) -> usize {
+    self.activity.speed
+  }
+}
+
+struct Situation {
+  position: Point,
+  state: AiState,
+  opponent: Option<(Id, Point)>,
+  desired_position: Option<Point>,
+  range: Option<(i32, i32)>,
+}
+
+pub fn score_ai_activities(world: &World, id: Id) -> Vec<Plan> {
+  let Some(ai) = world.ai.get(&id) else {
+    return vec![];
+  };
+  let Some(position) = world.position.get_right(&id).copied() else {
+    return vec![];
+  };
+  let opponent = match ai.state {
+    AiState::Hunting(target) | AiState::Fleeing(target) => {
+      world.position.get_right(&target).map(|p| (target, *p))
+    }
+    _ => None,
+  };
+  let situation = Situation {
+    position,
+    state: ai.state,
+    opponent,
+    desired_position: desired_position(world, id),
+    range: ranged_band(world, id),
+  };
+  let mut plans = vec![];
+  for (source_id, activity) in collect_activities(world, id) {
+    for direction in plan_directions(&situation, activity) {
+      let value = score_activity(world, id, &situation, source_id, activity, direction);
+      if value <= 0 || validate_activity(world, id, activity, direction).is_err() {
+        continue;
+      }
+      plans.push(Plan {
+        source_id,
+        activity: *activity,
+        direction,
+        score: value * 100 / activity.speed.max(1) as i32,
+      });
+    }
+  }
+  plans.sort_by_key(|plan| std::cmp::Reverse(plan.score));
+  plans
+}
+
+fn plan_directions(situation: &Situation, activity: &Activity) -> Vec<(i32, i32)> {
+  match (activity.activity_type, situation.opponent) {
+    (ActivityType::Throw(_), Some((_, p))) => {
+      vec![(p.0 - situation.position.0, p.1 - situation.position.1)]
+    }
+    (ActivityType::Throw(_), None) => vec![],
+    _ => PLAN_DIRECTIONS.to_vec(),
+  }
+}
+
+pub fn plan_ai_activity(world: &World, id: Id) -> Option<Plan> {
+  decide_ai_activity(world, id).map(|(plan, _)| plan)
+}
+
+pub fn decide_ai_activity(world: &World, id: Id) -> Option<(Plan, Vec<usize>)> {
+  if let Some(decision) = run_behavior(world, id) {
+    return Some(decision);
+  }
+  let plan = score_ai_activities(world, id).into_iter().next()?;
+  Some((plan, vec![]))
+}
+
+fn score_activity(
+  world: &World,
+  id: Id,
+  situation: &Situation,
+  source_id: Id,
+  activity: &Activity,
+  direction: (i32, i32),
+) -> i32 {
+  let target = (situation.position.0 + direction.0, situation.position.1 + direction.1);
+  let is_desired = situation.desired_position == Some(target) && direction != (0, 0);
+  let adjacent_opponent = situation
+    .opponent
+    .filter(|(_, p)| *p == target && direction != (0, 0))
+    .map(|(opponent_id, _)| opponent_id);
+  match activity.activity_type {
+    ActivityType::Wait() if direction == (0, 0) => match situation.state {
+      AiState::Sleeping => 5,
+      _ => 1,
+    },
+    ActivityType::Wait() => 0,
+    ActivityType::Step() if is_desired && !knows_closed_door_at(world, id, target) => 10,
+    ActivityType::Step() => 0,
+    ActivityType::Interact() if is_desired && knows_closed_door_at(world, id, target) => 12,
+    ActivityType::Interact() => 0,
+    ActivityType::MeleeAttack(min, max, _) => {
+      let Some(opponent_id) = adjacent_opponent else {
+        return 0;
+      };
+      let is_cornered = situation.desired_position.is_none();
+      let is_fleeing = matches!(situation.state, AiState::Fleeing(_));
+      if (is_fleeing || situation.range.is_some()) && !is_cornered {
+        return 0;
+      }
+      let bonus = item_damage(world, source_id);
+      let average = (min + max) / 2 + bonus;
+      20 + average * hit_chance(world, id, source_id, opponent_id) / 10
+    }
+    ActivityType::RangedAttack(min, max, _, range) => {
+      let Some((opponent_id, opponent_position)) = situation.opponent else {
+        return 0;
+      };
+      if !matches!(situation.state, AiState::Hunting(_)) || direction == (0, 0) {
+        return 0;
+      }
+      let is_cornered = situation.desired_position.is_none();
+      let min_distance = situation.range.map_or(2, |(min, _)| min);
+      if chebyshev(situation.position, opponent_position) < min_distance && !is_cornered {
+        return 0;
+      }
+      if find_shot_target(world, id, situation.position, direction, range) != Some(opponent_id) {
+        return 0;
+      }
+      let bonus = item_damage(world, source_id);
+      let average = (min + max) / 2 + bonus;
+      20 + average * hit_chance(world, id, source_id, opponent_id) / 10
+    }
+    ActivityType::Shove(_) => match (situation.state, adjacent_opponent) {
+      (AiState::Fleeing(_), Some(_)) => 15,
+      _ => 0,
+    },
+    ActivityType::Throw(range) => {
+      let Some((opponent_id, opponent_position)) = situation.opponent else {
+        return 0;
+      };
+      if !matches!(situation.state, AiState::Hunting(_)) || direction == (0, 0) {
+        return 0;
+      }
+      if chebyshev(situation.position, opponent_position) < 2 {
+        return 0;
+      }
+      if is_only_weapon(world, id, source_id) {
+        return 0;
+      }
+      let (_, hit_id) = trace_projectile(world, id, situation.position, target, range);
+      if hit_id != Some(opponent_id) {
+        return 0;
+      }
+      let (damage, _) = thrown_damage(world, source_id);
+      let value = 8 + 4 * (damage.0 + damage.1) / 2;
+      if world.provides_activity.contains_key(&source_id) {
+        value / 3
+      } else {
+        value
+      }
+    }
+    ActivityType::PickUp() => {
+      let is_idle = matches!(
+        situation.state,
+        AiState::Wandering(_) | AiState::Searching(_) | AiState::Returning(_)
+      );
+      if is_idle && knows_item_at(world, id, target) {
+        6
+      } else {
+        0
+      }
+    }
+    ActivityType::Use(effect) if direction == (0, 0) => score_effect(world, id, situation, effect),
+    ActivityType::Use(_) => 0,
+  }
+}
+
+fn score_effect(world: &World, id: Id, situation: &Situation, effect: Effect) -> i32 {
+  match effect {
+    Effect::Heal(amount) => {
+      let (Some(health), Some(maximum)) = (world.health.get(&id), world.max_health.get(&id)) else {
+        return 0;
+      };
+      let healed = amount.min(maximum - health);
+      if healed <= 0 {
+        return 0;
+      }
+      let urgency = if is_badly_hurt(world, id) { 15 } else { 0 };
+      6 * healed + urgency
+    }
+    Effect::Cure => {
+      let statuses = world.statuses.get(&id).into_iter().flatten();
+      let harmful = statuses.filter(|status| status.modifier.is_harmful()).count();
+      10 * harmful as i32
+    }
+    Effect::Buff(modifier, _) => {
+      let Some((_, opponent_position)) = situation.opponent else {
+        return 0;
+      };
+      let mut statuses = world.statuses.get(&id).into_iter().flatten();
+      let is_active = statuses.any(|status| status.modifier == modifier);
+      if is_active || chebyshev(situation.position, opponent_position) > 3 {
+        return 0;
+      }
+      8
+    }
+  }
+}
+
+fn is_only_weapon(world: &World, id: Id, item_id: Id) -> bool {
+  let is_weapon = |activity: &Activity| {
+    matches!(
+      activity.activity_type,
+      ActivityType::MeleeAttack(..) | ActivityType::RangedAttack(..)
+    )
+  };
+  let provides_weapon = |source_id: Id| {
+    world
+      .provides_activity
+      .get(&source_id)
+      .is_some_and(|activities| activities.iter().any(is_weapon))
+  };
+  provides_weapon(item_id)
+    && !std::iter::once(id)
+      .chain(held_items(world, id).copied())
+      .any(|source_id| source_id != item_id && provides_weapon(source_id))
+}
@@ -0,0 +1,136 @@
+use crate::*;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct Experience {
+  pub points: i32,
+  pub level: i32,
+  pub unspent: i32,
+}
+
+impl Experience {
+  pub fn new() -> Self {
+    Self {
+      points: 0,
+      level: 1,
+      unspent: 0,
+    }
+  }
+
+  pub fn next_level_points(&self) -> i32 {
+    10 * self.level * self.level
+  }
+}
+
+impl Default for Experience {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Display for Experience {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}/{}", self.points, self.next_level_points())
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpgradeType {
+  MaxHealth(i32),
+  MaxPool(Resource, i32),
+  Learn(Activity),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Upgrade {
+  pub name: &'static str,
+  pub level: i32,
+  pub upgrade_type: UpgradeType,
+}
+
+pub fn available_upgrades(world: &World, id: Id) -> Vec<Upgrade> {
+  let Some(experience) = world.experience.get(&id) else {
+    return vec![];
+  };
+  let mut upgrades = world
+    .upgrades
+    .get(&id)
+    .into_iter()
+    .flat_map(|upgrades| upgrades.iter())
+    .filter(|upgrade| upgrade.level <= experience.level)
+    .copied()
+    .collect::<Vec<_>>();
+  upgrades.sort_by_key(|upgrade| (upgrade.level, upgrade.name));
+  upgrades
+}
+
+pub fn has_unspent_upgrades(world: &World, id: Id) -> bool {
+  let Some(experience) = world.experience.get(&id) else {
+    return false;
+  };
+  experience.unspent > 0 && !available_upgrades(world, id).is_empty()
+}
+
+pub fn update_kill_experience(world: &mut World, attacker: Id, defender: Id) {
+  let Some(reward) = world.experience_reward.get(&defender).copied() else {
+    return;
+  };
+  let Some(experience) = world.experience.get_mut(&attacker) else {
+    return;
+  };
+  experience.points += reward;
+  let mut gained = 0;
+  while experience.points >= experience.next_level_points() {
+    experience.level += 1;
+    experience.unspent += 1;
+    gained += 1;
+  }
+  if gained == 0 {
+    return;
+  }
+  let level = experience.level;
+  if let Some(maximum) = world.max_health.get_mut(&attacker) {
+    *maximum += gained;
+  }
+  if let Some(health) = world.health.get_mut(&attacker) {
+    *health += gained;
+  }
+  log!("PROGRESSION", "level up", attacker, level);
+  if attacker == world.view_target {
+    world.message(format!("you reach level {level}"));
+  }
+}
+
+pub fn update_upgrade(world: &mut World, id: Id, upgrade: Upgrade) {
+  let Some(experience) = world.experience.get_mut(&id) else {
+    return;
+  };
+  if experience.unspent <= 0 {
+    return;
+  }
+  experience.unspent -= 1;
+  world.upgrades.remove_by_right(&id, &upgrade);
+  match upgrade.upgrade_type {
+    UpgradeType::MaxHealth(amount) => {
+      if let Some(maximum) = world.max_health.get_mut(&id) {
+        *maximum += amount;
+      }
+      if let Some(health) = world.health.get_mut(&id) {
+        *health += amount;
+      }
+    }
+    UpgradeType::MaxPool(resource, amount) => {
+      if let Some(pool) = pools_mut(world, resource).get_mut(&id) {
+        pool.maximum += amount;
+        pool.restore(amount);
+      }
+    }
+    UpgradeType::Learn(activity) => {
+      world.provides_activity.insert(id, activity);
+    }
+  }
+  log!("PROGRESSION", "upgrade chosen", id, upgrade.name);
+  if id == world.view_target {
+    world.message(format!("you gain {}", upgrade.name.to_lowercase()));
+  }
+}
@@ -0,0 +1,51 @@
+#[derive(Debug)]
+pub struct Random(u32);
+
+impl Default for Random {
+  fn default() -> Self {
+    Self::new(1)
+  }
+}
+
+impl Random {
+  pub fn new(seed: u32) -> Self {
+    Self(seed)
+  }
+
+  pub fn bool(&mut self) -> bool {
+    self.0 = hash_u32(self.0);
+    self.0.is_multiple_of(2)
+  }
+
+  pub fn range(&mut self, a: i32, b: i32) -> i32 {
+    self.0 = hash_u32(self.0);
+    a + ((self.0 as u64 * (b - a) as u64) >> 32) as i32
+  }
+
+  pub fn chance(&mut self, percent: i32) -> bool {
+    self.range(0, 100) < percent
+  }
+}
+
+pub const fn hash_u32(x: u32) -> u32 {
+  let mut v = x;
+  v ^= v.wrapping_shr(16);
+  v = v.wrapping_mul(0x85ebca6b);
+  v ^= v.wrapping_shr(13);
+  v = v.wrapping_mul(0xc2b2ae35);
+  v ^= v.wrapping_shr(16);
+  v
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn range_excludes_the_upper_bound() {
+    let mut random = Random::new(7);
+    let rolls = (0..10_000).map(|_| random.range(-2, 3)).collect::<Vec<_>>();
+    assert!(rolls.iter().all(|roll| (-2..3).contains(roll)));
+    assert!(rolls.contains(&-2) && rolls.contains(&2));
+  }
+}
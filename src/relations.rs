@@ -160,6 +160,10 @@ where
     self.by_right.get(right)
   }
 
+  pub fn iter(&self) -> hash_map::Iter<'_, L, R> {
+    self.by_left.iter()
+  }
+
   pub fn insert(&mut self, left: L, right: R) -> Option<R> {
     let previous_right = self.remove_by_left(&left);
     self.by_left.insert(left, right);
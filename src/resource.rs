@@ -0,0 +1,117 @@
+use crate::*;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+  Stamina,
+  Mana,
+}
+
+impl Resource {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Stamina => "stamina",
+      Self::Mana => "mana",
+    }
+  }
+
+  pub fn abbreviation(&self) -> &'static str {
+    match self {
+      Self::Stamina => "st",
+      Self::Mana => "mp",
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct Pool {
+  pub current: i32,
+  pub maximum: i32,
+  pub regeneration: usize,
+  pub progress: usize,
+}
+
+impl Pool {
+  pub fn new(maximum: i32, regeneration: usize) -> Self {
+    Self {
+      current: maximum,
+      maximum,
+      regeneration,
+      progress: 0,
+    }
+  }
+
+  pub fn restore(&mut self, amount: i32) {
+    self.current = (self.current + amount).min(self.maximum);
+  }
+
+  fn regenerate(&mut self, elapsed: usize) {
+    if self.regeneration == 0 {
+      return;
+    }
+    self.progress += elapsed;
+    let amount = self.progress / self.regeneration;
+    self.progress %= self.regeneration;
+    self.restore(amount as i32);
+  }
+}
+
+impl fmt::Display for Pool {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}/{}", self.current, self.maximum)
+  }
+}
+
+pub fn pools(world: &World, resource: Resource) -> &HasOne<Id, Pool> {
+  match resource {
+    Resource::Stamina => &world.stamina,
+    Resource::Mana => &world.mana,
+  }
+}
+
+pub fn pools_mut(world: &mut World, resource: Resource) -> &mut HasOne<Id, Pool> {
+  match resource {
+    Resource::Stamina => &mut world.stamina,
+    Resource::Mana => &mut world.mana,
+  }
+}
+
+pub fn can_afford(world: &World, id: Id, activity: &Activity) -> bool {
+  let Some((resource, amount)) = activity.cost else {
+    return true;
+  };
+  let Some(pool) = pools(world, resource).get(&id) else {
+    return false;
+  };
+  pool.current >= amount
+}
+
+pub fn update_activity_cost(world: &mut World, id: Id, activity: &Activity) {
+  let Some((resource, amount)) = activity.cost else {
+    return;
+  };
+  if let Some(pool) = pools_mut(world, resource).get_mut(&id) {
+    pool.current = (pool.current - amount).max(0);
+  }
+}
+
+pub fn update_rest(world: &mut World, id: Id) {
+  for resource in [Resource::Stamina, Resource::Mana] {
+    if let Some(pool) = pools_mut(world, resource).get_mut(&id) {
+      pool.restore(1);
+    }
+  }
+}
+
+pub fn update_resources(world: &mut World) {
+  let elapsed = world.time.saturating_sub(world.resource_time);
+  world.resource_time = world.time;
+  if elapsed == 0 {
+    return;
+  }
+  for resource in [Resource::Stamina, Resource::Mana] {
+    for (_, pool) in pools_mut(world, resource).iter_mut() {
+      pool.regenerate(elapsed);
+    }
+  }
+}
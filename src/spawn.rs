@@ -0,0 +1,120 @@
+use crate::*;
+
+const GOBLIN_MEMORY: usize = 200;
+// Two hit points let a wounded goblin live long enough to flee.
+const GOBLIN_HEALTH: i32 = 2;
+const ARCHER_RANGE: (i32, i32) = (3, 5);
+
+pub fn spawn_goblin(world: &mut World, icon: char, position: (i32, i32)) -> Id {
+  let id = Id::new();
+  world.name.insert(id, "Goblin");
+  world.icon.insert(id, icon);
+  world.layer.insert(id, Layer::Mob);
+  world.position.insert(id, position);
+  world.solidity.insert(id);
+  let goals = vec![
+    NavigationGoal::Player,
+    NavigationGoal::Faction(Faction::Villager),
+    NavigationGoal::Items,
+  ];
+  world.ai.insert(id, Ai::default().with_goals(goals));
+  world.memory.insert(id, Memory::new(GOBLIN_MEMORY).with_post(position));
+  if let Some(cache) = &world.ai_visibility {
+    world.fov.insert(id, FieldOfView::new(cache.clone()));
+  }
+  world.faction.insert(id, Faction::Goblin);
+  world.health.insert(id, GOBLIN_HEALTH);
+  world.max_health.insert(id, GOBLIN_HEALTH);
+  world.evasion.insert(id, 10);
+  world.corpse.insert(id, "goblin corpse");
+  world.experience_reward.insert(id, 4);
+  world
+    .damage_modifiers
+    .insert(id, DamageModifier::Resistance(DamageType::Fire, -100));
+  world.timeline.push(world.time, turn::Ai::new_turn(id));
+  world.provides_activity.insert(
+    id,
+    Activity {
+      name: "Walk",
+      speed: 10,
+      cost: None,
+      activity_type: ActivityType::Step(),
+    },
+  );
+  world.provides_activity.insert(
+    id,
+    Activity {
+      name: "Lurk",
+      speed: 5,
+      cost: None,
+      activity_type: ActivityType::Wait(),
+    },
+  );
+  world.provides_activity.insert(
+    id,
+    Activity {
+      name: "Open",
+      speed: 5,
+      cost: None,
+      activity_type: ActivityType::Interact(),
+    },
+  );
+  world.provides_activity.insert(
+    id,
+    Activity {
+      name: "Grab",
+      speed: 5,
+      cost: None,
+      activity_type: ActivityType::PickUp(),
+    },
+  );
+  let club = {
+    let id = Id::new();
+    world.name.insert(id, "Crude Club");
+    world.icon.insert(id, '!');
+    world.layer.insert(id, Layer::Item);
+    world.provides_activity.insert(
+      id,
+      Activity {
+        name: "Wallop",
+        speed: 10,
+        cost: None,
+        activity_type: ActivityType::MeleeAttack(1, 1, DamageType::Blunt),
+      },
+    );
+    world.durability.insert(id, 8);
+    world.knockback.insert(id, 1);
+    id
+  };
+  world.held_by.insert(club, id);
+  id
+}
+
+pub fn spawn_goblin_archer(world: &mut World, icon: char, position: (i32, i32)) -> Id {
+  let id = spawn_goblin(world, icon, position);
+  world.name.insert(id, "Goblin Archer");
+  if let Some(ai) = world.ai.get_mut(&id) {
+    ai.range = Some(ARCHER_RANGE);
+    ai.goals.retain(|goal| *goal != NavigationGoal::Items);
+    ai.goals.push(NavigationGoal::Exits);
+  }
+  let bow = {
+    let id = Id::new();
+    world.name.insert(id, "Short Bow");
+    world.icon.insert(id, '}');
+    world.layer.insert(id, Layer::Item);
+    world.provides_activity.insert(
+      id,
+      Activity {
+        name: "Shoot",
+        speed: 10,
+        cost: None,
+        activity_type: ActivityType::RangedAttack(1, 2, DamageType::Piercing, ARCHER_RANGE.1),
+      },
+    );
+    world.durability.insert(id, 20);
+    id
+  };
+  world.held_by.insert(bow, id);
+  id
+}
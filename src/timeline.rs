@@ -18,6 +18,10 @@ impl<T> Timeline<T> {
     self.0.pop().map(|s| (s.0, s.1))
   }
 
+  pub fn drain(&mut self) -> impl Iterator<Item = (usize, T)> + '_ {
+    self.0.drain().map(|s| (s.0, s.1))
+  }
+
   pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
     self.0.iter().map(|s| (s.0, &s.1))
   }
@@ -1,5 +1,7 @@
 use crate::*;
 
+const AI_IDLE_TIME: usize = 10;
+
 #[derive(Debug)]
 pub enum TurnType {
   Player(Player),
@@ -18,17 +20,28 @@ impl TurnType {
 #[derive(Debug)]
 pub struct Player {
   pub selected_activity_index: usize,
+  pub selected_upgrade_index: usize,
+  pub travel: Option<Path>,
+  pub aim: Option<(i32, i32)>,
 }
 
 impl Player {
   pub fn new_turn(id: Id) -> Event {
-    let turn = Self { selected_activity_index: 0 };
+    let turn = Self {
+      selected_activity_index: 0,
+      selected_upgrade_index: 0,
+      travel: None,
+      aim: None,
+    };
     Event::Turn(id, TurnType::Player(turn))
   }
 
   pub fn next_turn(self, id: Id) -> Event {
     let turn = Self {
-      selected_activity_index: self.selected_activity_index
+      selected_activity_index: self.selected_activity_index,
+      selected_upgrade_index: 0,
+      travel: self.travel,
+      aim: None,
     };
     Event::Turn(id, TurnType::Player(turn))
   }
@@ -37,14 +50,34 @@ impl Player {
     let Some(controls) = world.controls.get(&id) else {
       return;
     };
+    if has_unspent_upgrades(world, id) {
+      let result = match world.input.take_or_request() {
+        Some(i) if i == controls.act_center => self.choose_upgrade(world, id),
+        Some(i) if i == controls.activity_previous => self.select_upgrade(world, id, -1),
+        Some(i) if i == controls.activity_next => self.select_upgrade(world, id, 1),
+        _ => self,
+      };
+      world.current_event = Some(Event::Turn(id, TurnType::Player(result)));
+      return;
+    }
+    if self.travel.is_some() {
+      let result = self.travel(world, id);
+      world.current_event = result.map(|r| Event::Turn(id, TurnType::Player(r)));
+      return;
+    }
     let result = match world.input.take_or_request() {
-      Some(i) if i == controls.act_up => self.act(world, id, (0, -1)),
-      Some(i) if i == controls.act_down => self.act(world, id, (0, 1)),
-      Some(i) if i == controls.act_left => self.act(world, id, (-1, 0)),
-      Some(i) if i == controls.act_right => self.act(world, id, (1, 0)),
-      Some(i) if i == controls.act_center => self.act(world, id, (0, 0)),
+      Some(i) if i == controls.act_up => self.act_or_aim(world, id, (0, -1)),
+      Some(i) if i == controls.act_down => self.act_or_aim(world, id, (0, 1)),
+      Some(i) if i == controls.act_left => self.act_or_aim(world, id, (-1, 0)),
+      Some(i) if i == controls.act_right => self.act_or_aim(world, id, (1, 0)),
+      Some(i) if i == controls.act_center => self.act_or_aim(world, id, (0, 0)),
+      Some(i) if Some(i) == controls.act_up_left => self.act_or_aim(world, id, (-1, -1)),
+      Some(i) if Some(i) == controls.act_up_right => self.act_or_aim(world, id, (1, -1)),
+      Some(i) if Some(i) == controls.act_down_left => self.act_or_aim(world, id, (-1, 1)),
+      Some(i) if Some(i) == controls.act_down_right => self.act_or_aim(world, id, (1, 1)),
       Some(i) if i == controls.activity_previous => self.select_activity(world, id, -1),
       Some(i) if i == controls.activity_next => self.select_activity(world, id, 1),
+      Some(i) if Some(i) == controls.travel_down => self.start_travel(world, id),
       _ => Some(self),
     };
     world.current_event = result.map(|r| Event::Turn(id, TurnType::Player(r)));
@@ -63,32 +96,127 @@ impl Player {
     } else {
         self.selected_activity_index.saturating_add(magnitude) % total
     };
-    Some(Self{ selected_activity_index: index })
+    Some(Self {
+      selected_activity_index: index,
+      aim: None,
+      ..self
+    })
+  }
+
+  fn select_upgrade(self, world: &World, id: Id, delta: i32) -> Self {
+    let total = available_upgrades(world, id).len() as i32;
+    let index = (self.selected_upgrade_index as i32 + delta).rem_euclid(total.max(1));
+    Self {
+      selected_upgrade_index: index as usize,
+      ..self
+    }
+  }
+
+  fn choose_upgrade(self, world: &mut World, id: Id) -> Self {
+    if let Some(upgrade) = available_upgrades(world, id).get(self.selected_upgrade_index) {
+      update_upgrade(world, id, *upgrade);
+    }
+    Self {
+      selected_upgrade_index: 0,
+      ..self
+    }
+  }
+
+  fn start_travel(self, world: &mut World, id: Id) -> Option<Self> {
+    let stairs = world.dungeon.stairs_down.get(&world.dungeon.depth).copied();
+    let Some(stairs) = stairs.filter(|p| is_explored(world, *p)) else {
+      world.message("you don't know the way down");
+      return Some(self);
+    };
+    let Some(path) = find_travel_path(world, id, stairs) else {
+      world.message("there is no known path there");
+      return Some(self);
+    };
+    Some(Self {
+      travel: Some(path),
+      ..self
+    })
+  }
+
+  fn travel(mut self, world: &mut World, id: Id) -> Option<Self> {
+    let stop = |player: Self| {
+      Some(Self {
+        travel: None,
+        ..player
+      })
+    };
+    if is_threatened(world, id) {
+      world.message("you stop travelling");
+      return stop(self);
+    }
+    let Some(position) = world.position.get_right(&id).copied() else {
+      return stop(self);
+    };
+    let Some(next) = self.travel.as_ref().and_then(|path| path.peek()) else {
+      return stop(self);
+    };
+    let direction = (next.0 - position.0, next.1 - position.1);
+    let activity = collect_activities(world, id)
+      .filter(|(_, activity)| match activity.activity_type {
+        ActivityType::Interact() => is_closed_door_at(world, next),
+        ActivityType::Step() => true,
+        _ => false,
+      })
+      .find(|(_, activity)| validate_activity(world, id, activity, direction).is_ok())
+      .map(|(source_id, activity)| (source_id, *activity));
+    let Some((source_id, activity)) = activity else {
+      return stop(self);
+    };
+    if let (ActivityType::Step(), Some(path)) = (activity.activity_type, self.travel.as_mut()) {
+      path.next();
+    }
+    update_activity(world, id, source_id, activity, direction);
+    if self.travel.as_ref().is_some_and(|path| path.remaining() == 0) {
+      self.travel = None;
+    }
+    world.timeline.push(world.time + activity.speed, self.next_turn(id));
+    None
+  }
+
+  fn act_or_aim(self, world: &mut World, id: Id, direction: (i32, i32)) -> Option<Self> {
+    let activity = collect_activities(world, id).nth(self.selected_activity_index);
+    let Some(ActivityType::Throw(range)) = activity.map(|(_, a)| a.activity_type) else {
+      return self.act(world, id, direction);
+    };
+    if direction != (0, 0) {
+      let aim = self.aim.unwrap_or((0, 0));
+      let aim = (
+        (aim.0 + direction.0).clamp(-range, range),
+        (aim.1 + direction.1).clamp(-range, range),
+      );
+      return Some(Self {
+        aim: Some(aim),
+        ..self
+      });
+    }
+    match self.aim {
+      Some(aim) => self.act(world, id, aim),
+      None => {
+        world.message(ActivityFailure::NoTarget.to_string());
+        Some(self)
+      }
+    }
   }
 
   fn act(self, world: &mut World, id: Id, direction: (i32, i32)) -> Option<Self> {
     let activity = collect_activities(world, id).nth(self.selected_activity_index);
-    let (_, activity) = activity?;
+    let Some((source_id, activity)) = activity else {
+      return Some(self);
+    };
     let activity = *activity;
-    match activity.activity_type {
-      ActivityType::Wait() => {
-        world.timeline.push(world.time + activity.speed, self.next_turn(id));
-      }
-      ActivityType::Step() => {
-        let action = Action::Move(direction);
-        update_action(world, id, action);
-        world.timeline.push(world.time + activity.speed, self.next_turn(id));
-      },
-      ActivityType::MeleeAttack(damage) => {
-        let action = Action::Attack(direction, damage);
-        update_action(world, id, action);
-        world.timeline.push(world.time + activity.speed, self.next_turn(id));
-      },
+    if let Err(failure) = validate_activity(world, id, &activity, direction) {
+      world.message(failure.to_string());
+      return Some(self);
     }
+    update_activity(world, id, source_id, activity, direction);
+    world.timeline.push(world.time + activity.speed, self.next_turn(id));
     None
   }
-
-
 }
 
 #[derive(Debug)]
@@ -100,18 +228,23 @@ impl Ai {
   }
 
   fn update(self, world: &mut World, id: Id) {
-    if let (_, Some(action)) = pick_ai_action(world, id) {
-      update_action(world, id, action);
-    }
-    let (speed, action) = pick_ai_action(world, id);
-    if action.is_some() {
-      world.timeline.push(
-        world.time + speed,
-        Event::Turn(
-          id,
-          TurnType::Ai(Self()),
-        ),
-      );
+    if !world.ai.contains_key(&id) {
+      return;
     }
+    update_ai_state(world, id);
+    let Some((plan, cooldowns)) = decide_ai_activity(world, id) else {
+      world.timeline.push(world.time + AI_IDLE_TIME, Self::new_turn(id));
+      return;
+    };
+    update_behavior_cooldowns(world, id, &cooldowns);
+    update_activity(world, id, plan.source_id, plan.activity, plan.direction);
+    world.timeline.push(world.time + plan.time_cost(), Self::new_turn(id));
   }
 }
+
+fn is_threatened(world: &World, id: Id) -> bool {
+  world
+    .ai
+    .iter()
+    .any(|(ai_id, _)| is_hostile(world, *ai_id, id) && can_see(world, id, *ai_id) == Some(true))
+}
@@ -1,6 +1,8 @@
 use crate::*;
 use std::fmt;
 
+const PLANNER_ROWS: usize = 4;
+
 pub fn update_ui(world: &mut World) {
   let ui = row(vec![
     border(
@@ -12,12 +14,20 @@ pub fn update_ui(world: &mut World) {
           text(" "),
           world_stats(world),
           text(" "),
+          last_hit(world),
+          text(" "),
           expand_height(timeline(world)),
         ]),
       ),
     ),
-    flex(expand_width(expand_height(viewport(world.viewport_id)))),
-    turn_controls(world),
+    flex(column(vec![
+      flex(expand_width(expand_height(viewport(world.viewport_id)))),
+      messages(world),
+    ])),
+    match world.view_type {
+      ViewType::Planner => planner(world),
+      _ => turn_controls(world),
+    },
   ]);
   world.ui.update(ui);
 }
@@ -38,18 +48,95 @@ fn player_stats(world: &World) -> WidgetFn<'static> {
     let value = value.map(|v| v.to_string()).unwrap_or_else(|| "-".into());
     row(vec![text(name), flex(expand_width(text(" "))), text(value)])
   }
-  column(vec![
+  let health = world.health.get(&target_id).map(|health| {
+    match world.max_health.get(&target_id) {
+      Some(maximum) => format!("{health}/{maximum}"),
+      None => health.to_string(),
+    }
+  });
+  let mut stats = vec![
     stat("Name:", world.name.get(&target_id)),
-    stat("Health:", world.health.get(&target_id)),
-  ])
+    stat("Health:", health),
+    stat("Stamina:", world.stamina.get(&target_id)),
+    stat("Mana:", world.mana.get(&target_id)),
+    stat("Level:", world.experience.get(&target_id).map(|e| e.level)),
+    stat("XP:", world.experience.get(&target_id)),
+  ];
+  for status in world.statuses.get(&target_id).into_iter().flatten() {
+    let remaining = status.expires_at.saturating_sub(world.time);
+    stats.push(row(vec![
+      text(status.modifier.to_string()),
+      flex(expand_width(text(" "))),
+      text(format!("{remaining}t")),
+    ]));
+  }
+  column(stats)
+}
+
+fn messages(world: &World) -> WidgetFn<'static> {
+  let lines = world
+    .messages
+    .iter()
+    .rev()
+    .take(3)
+    .rev()
+    .map(|message| text(message.clone()))
+    .collect();
+  border((0, 0, 1, 0), expand_width(column(lines)))
 }
 
 fn world_stats(world: &World) -> WidgetFn<'static> {
-  column(vec![row(vec![
-    text("Time:"),
+  column(vec![
+    row(vec![
+      text("Depth:"),
+      flex(expand_width(text(" "))),
+      text((world.dungeon.depth + 1).to_string()),
+    ]),
+    row(vec![
+      text("Time:"),
+      flex(expand_width(text(" "))),
+      text(world.time.to_string()),
+    ]),
+  ])
+}
+
+fn last_hit(world: &World) -> WidgetFn<'static> {
+  let Some(hit) = &world.last_hit else {
+    return column(vec![text("Last hit:"), text("-")]);
+  };
+  let mut lines = vec![
+    text("Last hit:"),
+    text(format!("{} > {}", hit.attacker, hit.defender)),
+    row(vec![
+      text(format!("Roll {}%", hit.chance)),
+      flex(expand_width(text(" "))),
+      text(match hit.roll {
+        HitRoll::Miss => "miss",
+        HitRoll::Hit => "hit",
+        HitRoll::Critical => "crit",
+      }),
+    ]),
+  ];
+  for step in hit.steps.iter() {
+    lines.push(row(vec![
+      text(format!("{} ({})", step.label, step.source)),
+      flex(expand_width(text(" "))),
+      text(format!("{:+}", step.amount)),
+    ]));
+  }
+  lines.push(row(vec![
+    text(format!("Total {}", hit.damage_type.name())),
     flex(expand_width(text(" "))),
-    text(world.time.to_string()),
-  ])])
+    text(hit.total.to_string()),
+  ]));
+  if let Some(impact) = &world.last_impact {
+    lines.push(row(vec![
+      text(format!("Impact ({})", impact.defender)),
+      flex(expand_width(text(" "))),
+      text(format!("{:+}", impact.total)),
+    ]));
+  }
+  column(lines)
 }
 
 fn timeline(world: &World) -> WidgetFn<'static> {
@@ -63,8 +150,12 @@ fn timeline(world: &World) -> WidgetFn<'static> {
         let description = match turn {
           TurnType::Player(_) => "turn".to_string(),
           TurnType::Ai(_) => {
-            let (_, predicted_action) = pick_ai_action(world, *id);
-            format_action_description(&predicted_action)
+            let plan = plan_ai_activity(world, *id);
+            let description = format_activity_description(&plan);
+            match world.ai.get(id) {
+              Some(ai) => format!("{}: {description}", ai.state.name()),
+              None => description,
+            }
           },
         };
         (icon, description)
@@ -103,20 +194,37 @@ fn turn_controls(world: &World) -> WidgetFn<'static> {
   let Event::Turn(id, TurnType::Player(turn)) = current_event else {
     return column(vec![]);
   };
+  if has_unspent_upgrades(world, *id) {
+    return level_up(world, *id, turn.selected_upgrade_index);
+  }
   let activities = collect_activities(world, *id)
     .enumerate()
-    .map(|(i, (id, activity))| {
-      let from_name = world.name.get(&id).unwrap_or(&"???");
+    .map(|(i, (source_id, activity))| {
+      let from_name = world.name.get(&source_id).unwrap_or(&"???");
+      let charges = match world.charges.get(&source_id) {
+        Some(charges) => format!(" x{charges}"),
+        None => String::new(),
+      };
       let selector = if i == turn.selected_activity_index {
-        text("> ")
+        '>'
+      } else {
+        ' '
+      };
+      let availability = if can_afford(world, *id, activity) {
+        ' '
       } else {
-        text("  ")
+        'x'
+      };
+      let cost = match activity.cost {
+        Some((resource, amount)) => format!(" {}{}", amount, resource.abbreviation()),
+        None => String::new(),
       };
       row(vec![
-        selector,
+        text(format!("{selector}{availability}")),
         text(activity.name.to_string()),
         text(format!(" {}t", activity.speed)),
-        text(format!(" ({from_name})")),
+        text(cost),
+        text(format!(" ({from_name}{charges})")),
       ])
     })
     .collect();
@@ -129,10 +237,54 @@ fn turn_controls(world: &World) -> WidgetFn<'static> {
   )
 }
 
-fn format_action_description(action: &Option<Action>) -> String {
-  match action {
+fn level_up(world: &World, id: Id, selected_index: usize) -> WidgetFn<'static> {
+  let upgrades = available_upgrades(world, id)
+    .into_iter()
+    .enumerate()
+    .map(|(i, upgrade)| {
+      let selector = if i == selected_index { '>' } else { ' ' };
+      row(vec![
+        text(format!("{selector} {}", upgrade.name)),
+        text(format!(" (lv {})", upgrade.level)),
+      ])
+    })
+    .collect();
+  let unspent = world.experience.get(&id).map(|e| e.unspent).unwrap_or(0);
+  border(
+    (1, 0, 0, 0),
+    column(vec![
+      text(format!("Level up! ({unspent} to spend)")),
+      column(upgrades),
+    ])
+  )
+}
+
+fn format_activity_description(plan: &Option<Plan>) -> String {
+  match plan {
     None => "wait".to_string(),
-    Some(Action::Move(v)) => format!("move {},{}", v.0, v.1),
-    Some(Action::Attack(v, d)) => format!("attack {},{} {}", v.0, v.1, d),
+    Some(plan) => {
+      let v = plan.direction;
+      format!("{} {},{}", plan.activity.name.to_lowercase(), v.0, v.1)
+    }
+  }
+}
+
+fn planner(world: &World) -> WidgetFn<'static> {
+  let mut lines = vec![text("Planner:")];
+  let mut ids = world.ai.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+  ids.sort();
+  for id in ids {
+    if Some(true) != can_see(world, world.view_target, id) {
+      continue;
+    }
+    let (Some(icon), Some(ai)) = (world.icon.get(&id), world.ai.get(&id)) else {
+      continue;
+    };
+    lines.push(text(format!("{icon} {}", ai.state.name())));
+    for plan in score_ai_activities(world, id).iter().take(PLANNER_ROWS) {
+      let v = plan.direction;
+      lines.push(text(format!("  {} {},{} {}", plan.activity.name, v.0, v.1, plan.score)));
+    }
   }
+  border((1, 0, 0, 0), column(lines))
 }
@@ -131,6 +131,14 @@ impl FieldOfView {
     }
   }
 
+  pub fn visible_points(&self) -> impl Iterator<Item = Point> + '_ {
+    self
+      .lookup
+      .iter()
+      .filter(|(_, generation)| **generation == self.generation)
+      .map(|(point, _)| *point)
+  }
+
   pub fn is_visible(&self, point: Point) -> bool {
     let Some(generation) = self.lookup.get(&point) else {
       return false;
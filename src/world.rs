@@ -1,19 +1,35 @@
 use crate::*;
 use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::io;
+use std::rc::Rc;
+
+const MESSAGE_LIMIT: usize = 100;
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Layer {
   Map,
+  Corpse,
+  Feature,
+  Item,
   Mob,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NavigationGoal {
+  Player,
+  Faction(Faction),
+  Items,
+  Exits,
+}
+
 #[derive(Default)]
 pub enum ViewType {
   #[default]
   Normal,
   Navigation,
   Revealed,
+  Planner,
 }
 
 #[derive(Default)]
@@ -24,10 +40,16 @@ pub struct World {
   pub view_type: ViewType,
   pub tick: usize,
   pub time: usize,
+  pub resource_time: usize,
   pub timeline: Timeline<Event>,
   pub auto_step: Option<usize>,
   pub current_event: Option<Event>,
+  pub last_hit: Option<Hit>,
+  pub last_impact: Option<Hit>,
+  pub messages: Vec<String>,
   pub view_target: Id,
+  pub dungeon: Dungeon,
+  pub random: Random,
   pub name: HasOne<Id, &'static str>,
   pub icon: HasOne<Id, char>,
   pub layer: HasOne<Id, Layer>,
@@ -37,14 +59,49 @@ pub struct World {
   pub controls: HasOne<Id, Controls>,
   pub ai: HasOne<Id, Ai>,
   pub navigation: Navigation,
+  pub navigation_maps: HashMap<NavigationGoal, Navigation>,
+  pub explored: HashSet<Point>,
+  pub ai_visibility: Option<Rc<VisibilityCache>>,
+  pub flee_maps: HashMap<NavigationGoal, Navigation>,
+  pub navigation_seeds: HashMap<NavigationGoal, Vec<Point>>,
   pub health: HasOne<Id, i32>,
+  pub max_health: HasOne<Id, i32>,
   pub fov: HasOne<Id, FieldOfView>,
   pub held_by: ManyToOne<Id, Id>,
   pub provides_activity: HasMany<Id, Activity>,
+  pub durability: HasOne<Id, i32>,
+  pub damage: HasOne<Id, i32>,
+  pub components: ManyToOne<Id, Id>,
+  pub worn_by: ManyToOne<Id, Id>,
+  pub damage_modifiers: HasMany<Id, DamageModifier>,
+  pub accuracy: HasOne<Id, i32>,
+  pub evasion: HasOne<Id, i32>,
+  pub critical: HasOne<Id, i32>,
+  pub knockback: HasOne<Id, i32>,
+  pub stamina: HasOne<Id, Pool>,
+  pub mana: HasOne<Id, Pool>,
+  pub door: HasOne<Id, Door>,
+  pub lever: HasOne<Id, Lever>,
+  pub interaction: HasOne<Id, Interaction>,
+  pub triggered_by: ManyToOne<Id, Id>,
+  pub charges: HasOne<Id, i32>,
+  pub statuses: HasMany<Id, Status>,
+  pub corpse: HasOne<Id, &'static str>,
+  pub experience: HasOne<Id, Experience>,
+  pub experience_reward: HasOne<Id, i32>,
+  pub upgrades: HasMany<Id, Upgrade>,
+  pub faction: HasOne<Id, Faction>,
+  pub hostility: Hostility,
+  pub memory: HasOne<Id, Memory>,
+  pub behavior: HasOne<Id, Behavior>,
+  pub behavior_trees: HashMap<String, Rc<BehaviorTree>>,
 }
 
 impl World {
   pub fn remove_entity(&mut self, id: &Id) {
+    for dependent_id in dependents(self, *id) {
+      self.remove_entity(&dependent_id);
+    }
     self.name.remove(id);
     self.icon.remove(id);
     self.layer.remove(id);
@@ -54,14 +111,50 @@ impl World {
     self.controls.remove(id);
     self.ai.remove(id);
     self.health.remove(id);
+    self.max_health.remove(id);
     self.fov.remove(id);
     self.held_by.remove_by_left(id);
     self.held_by.remove_by_right(id);
     self.provides_activity.remove_by_left(id);
+    self.durability.remove(id);
+    self.damage.remove(id);
+    self.components.remove_by_left(id);
+    self.components.remove_by_right(id);
+    self.worn_by.remove_by_left(id);
+    self.worn_by.remove_by_right(id);
+    self.damage_modifiers.remove_by_left(id);
+    self.accuracy.remove(id);
+    self.evasion.remove(id);
+    self.critical.remove(id);
+    self.knockback.remove(id);
+    self.stamina.remove(id);
+    self.mana.remove(id);
+    self.door.remove(id);
+    self.lever.remove(id);
+    self.interaction.remove(id);
+    self.triggered_by.remove_by_left(id);
+    self.triggered_by.remove_by_right(id);
+    self.charges.remove(id);
+    self.statuses.remove_by_left(id);
+    self.corpse.remove(id);
+    self.experience.remove(id);
+    self.experience_reward.remove(id);
+    self.upgrades.remove_by_left(id);
+    self.faction.remove(id);
+    self.memory.remove(id);
+    self.behavior.remove(id);
+  }
+
+  pub fn message(&mut self, message: impl Into<String>) {
+    self.messages.push(message.into());
+    let excess = self.messages.len().saturating_sub(MESSAGE_LIMIT);
+    self.messages.drain(..excess);
   }
 
   pub fn startup(&mut self) {
     update_fov(self);
+    update_explored(self);
+    update_memories(self);
     update_navigation(self);
     update_timeline(self);
     update_ui(self);
@@ -83,9 +176,13 @@ impl World {
         self.input,
         self.current_event,
       );
+      instrument!("update_resources", update_resources(self));
+      instrument!("update_statuses", update_statuses(self));
       instrument!("update_current_event", update_current_event(self));
       instrument!("update_dead_entities", update_dead_entities(self));
       instrument!("update_fov", update_fov(self));
+      instrument!("update_explored", update_explored(self));
+      instrument!("update_memories", update_memories(self));
       instrument!("update_navigation", update_navigation(self));
       if self.input.is_requested() {
         break;
@@ -117,15 +214,26 @@ impl World {
       .get_right(&self.view_target)
       .unwrap_or(&(0, 0));
     let to_screen = ((-size.0 / 2) + view_position.0, (-size.1 / 2) + view_position.1);
+    let aim = match &self.current_event {
+      Some(Event::Turn(_, TurnType::Player(turn))) => turn.aim,
+      _ => None,
+    };
+    let aim = aim.map(|aim| (view_position.0 + aim.0, view_position.1 + aim.1));
     for column in 0..size.0 {
       for row in 0..size.1 {
         let cell_position = (to_screen.0 + column, to_screen.1 + row);
         let char = match self.view_type {
-          ViewType::Normal => draw_normal_cell(self, *view_position, cell_position),
+          ViewType::Normal | ViewType::Planner => {
+            draw_normal_cell(self, *view_position, cell_position)
+          }
           ViewType::Revealed => draw_revealed_cell(self, *view_position, cell_position),
           ViewType::Navigation => draw_navigation_cell(self, *view_position, cell_position),
         };
-        let char = char.unwrap_or(' ');
+        let char = if aim == Some(cell_position) {
+          'X'
+        } else {
+          char.unwrap_or(' ')
+        };
         let screen = (offset.0 + column, offset.1 + row);
         terminal.set(screen, char);
       }
@@ -153,7 +261,7 @@ fn draw_normal_cell(
 
 fn draw_revealed_cell(
   world: &World,
-  view_position: (i32, i32),
+  _view_position: (i32, i32),
   cell_position: (i32, i32),
 ) -> Option<char> {
   let ids = world.position.get_lefts(&cell_position)?;
@@ -168,7 +276,8 @@ fn draw_navigation_cell(
   _view_position: (i32, i32),
   cell_position: (i32, i32),
 ) -> Option<char> {
-  let value = world.navigation.get_value(cell_position)?;
+  let navigation = world.navigation_maps.get(&NavigationGoal::Player)?;
+  let value = navigation.get_value(cell_position)?;
   let char = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().nth(value);
   match char {
     Some(char) => Some(char),
@@ -194,6 +303,8 @@ fn update_view_type(world: &mut World) {
     world.view_type = ViewType::Navigation;
   } else if world.input.try_consume('3') {
     world.view_type = ViewType::Revealed;
+  } else if world.input.try_consume('4') {
+    world.view_type = ViewType::Planner;
   }
 }
 
@@ -206,10 +317,50 @@ fn update_dead_entities(world: &mut World) {
     .collect::<Vec<Id>>();
   for id in ids {
     update_dead_view_target(world, id);
+    update_dropped_items(world, id);
+    update_corpse(world, id);
     world.remove_entity(&id);
   }
 }
 
+fn dependents(world: &World, id: Id) -> Vec<Id> {
+  held_items(world, id)
+    .chain(worn_items(world, id))
+    .chain(item_components(world, id))
+    .copied()
+    .collect()
+}
+
+fn update_dropped_items(world: &mut World, id: Id) {
+  let Some(position) = world.position.get_right(&id).copied() else {
+    return;
+  };
+  let item_ids = held_items(world, id)
+    .chain(worn_items(world, id))
+    .copied()
+    .collect::<Vec<_>>();
+  for item_id in item_ids {
+    world.held_by.remove_by_left(&item_id);
+    world.worn_by.remove_by_left(&item_id);
+    world.position.insert(item_id, position);
+    log!("ITEM", "item dropped", item_id, world.name.get(&item_id));
+  }
+}
+
+fn update_corpse(world: &mut World, id: Id) {
+  let Some(name) = world.corpse.get(&id).copied() else {
+    return;
+  };
+  let Some(position) = world.position.get_right(&id).copied() else {
+    return;
+  };
+  let corpse_id = Id::new();
+  world.name.insert(corpse_id, name);
+  world.icon.insert(corpse_id, '%');
+  world.layer.insert(corpse_id, Layer::Corpse);
+  world.position.insert(corpse_id, position);
+}
+
 fn update_dead_view_target(world: &mut World, id: Id) {
   if id != world.view_target {
     return;
@@ -227,7 +378,7 @@ fn update_fov(world: &mut World) {
   for (id, fov) in world.fov.iter_mut() {
     let Some(position) = world.position.get_right(id) else {
       fov.update(|_| false);
-      return;
+      continue;
     };
     fov.update(|p| {
       let position = (position.0 + p.0, position.1 + p.1);
@@ -247,14 +398,111 @@ fn update_fov(world: &mut World) {
   }
 }
 
+fn update_explored(world: &mut World) {
+  let Some(fov) = world.fov.get(&world.view_target) else {
+    return;
+  };
+  let Some(position) = world.position.get_right(&world.view_target) else {
+    return;
+  };
+  for p in fov.visible_points() {
+    world.explored.insert((position.0 + p.0, position.1 + p.1));
+  }
+}
+
+fn navigation_seeds(world: &World, goal: NavigationGoal) -> Vec<Point> {
+  let positions = |ids: Vec<Id>| {
+    let mut positions = ids
+      .iter()
+      .filter_map(|id| world.position.get_right(id).copied())
+      .collect::<Vec<_>>();
+    positions.sort();
+    positions
+  };
+  match goal {
+    NavigationGoal::Player => {
+      let id = world.view_target;
+      if !world.health.contains_key(&id) {
+        return vec![];
+      }
+      positions(vec![id])
+    }
+    NavigationGoal::Faction(faction) => positions(
+      world
+        .faction
+        .iter()
+        .filter(|(id, f)| **f == faction && world.health.contains_key(id))
+        .map(|(id, _)| *id)
+        .collect(),
+    ),
+    NavigationGoal::Items => positions(
+      world
+        .layer
+        .iter()
+        .filter(|(_, layer)| **layer == Layer::Item)
+        .map(|(id, _)| *id)
+        .collect(),
+    ),
+    NavigationGoal::Exits => positions(
+      world
+        .interaction
+        .iter()
+        .filter(|(_, interaction)| matches!(interaction, Interaction::Stairs(_)))
+        .map(|(id, _)| *id)
+        .collect(),
+    ),
+  }
+}
+
 fn update_navigation(world: &mut World) {
-  world.navigation.reset();
-  if let Some(position) = world.position.get_right(&world.view_target) {
-    if world.health.contains_key(&world.view_target) {
-      world.navigation.set_value(*position, 0);
+  let mut goals = vec![NavigationGoal::Player];
+  for (_, ai) in world.ai.iter() {
+    for goal in ai.goals.iter() {
+      if !goals.contains(goal) {
+        goals.push(*goal);
+      }
+    }
+  }
+  world.navigation_maps.retain(|goal, _| goals.contains(goal));
+  world.flee_maps.retain(|goal, _| goals.contains(goal));
+  world.navigation_seeds.retain(|goal, _| goals.contains(goal));
+  for goal in goals {
+    let seeds = navigation_seeds(world, goal);
+    if world.navigation_seeds.get(&goal) == Some(&seeds) {
+      continue;
     }
+    world.navigation.reset();
+    let mut navigation = world.navigation.clone();
+    for seed in seeds.iter() {
+      if navigation.get_value(*seed).is_some() {
+        navigation.set_value(*seed, 0);
+      }
+    }
+    navigation.calculate();
+    world.flee_maps.insert(goal, navigation.flee_map());
+    world.navigation_maps.insert(goal, navigation);
+    world.navigation_seeds.insert(goal, seeds);
+  }
+}
+
+pub fn goal_for_target(world: &World, target: Id) -> Option<NavigationGoal> {
+  if target == world.view_target {
+    return Some(NavigationGoal::Player);
+  }
+  world.faction.get(&target).map(|faction| NavigationGoal::Faction(*faction))
+}
+
+pub fn has_line_of_sight(world: &World, a: Id, b: Id, range: i32) -> bool {
+  let (Some(a), Some(b)) = (world.position.get_right(&a), world.position.get_right(&b)) else {
+    return false;
+  };
+  if (b.0 - a.0).abs().max((b.1 - a.1).abs()) > range {
+    return false;
   }
-  world.navigation.calculate();
+  line(*a, *b).skip(1).take_while(|p| p != b).all(|p| {
+    let ids = world.position.get_lefts(&p);
+    !ids.is_some_and(|ids| ids.iter().any(|id| world.opacity.contains(id)))
+  })
 }
 
 pub fn can_see(world: &World, a: Id, b: Id) -> Option<bool> {